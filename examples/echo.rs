@@ -1,5 +1,6 @@
 use aruntime::{
     executor::Executor,
+    io::AsyncWriteExt,
     net::TcpListener,
 };
 
@@ -12,8 +13,9 @@ fn main() {
                 let mut buf = [0u8; 1024];
                 loop {
                     match stream.read(&mut buf).await {
+                        Ok(0) => break,
                         Ok(n) => {
-                            stream.write(&buf[..n]).await.unwrap();
+                            stream.write_all(&buf[..n]).await.unwrap();
                         }
                         Err(e) => {
                             println!("read err: {:?}", e);
@@ -24,7 +24,7 @@ fn main() {
             }
         }
 
-        let file3: File = std::fs::File::options().create(true).write(true).open("./test.txt").unwrap().into();
+        let file3: File = std::fs::File::options().create(true).write(true).truncate(true).open("./test.txt").unwrap().into();
         
         println!("file3 fd: {}", file3.as_raw_fd());
         let buf = b"Hello, world!\n";
@@ -0,0 +1,219 @@
+use std::{
+    cell::RefCell,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    ops::{Deref, DerefMut},
+    rc::Rc,
+};
+
+use crate::reactor::{current_reactor, ReactorRef};
+
+/// A set of buffers registered with the ring through `register_buffers`.
+///
+/// Reads and writes on checked-out [`FixedBuf`]s use `IORING_OP_READ_FIXED` and
+/// `IORING_OP_WRITE_FIXED`, so the kernel doesn't pin the pages for every op.
+/// A ring holds a single set of fixed buffers, so only one pool can be alive at a time.
+/// The memory stays registered until the pool and every buffer checked out of it are gone,
+/// including those still owned by an operation in flight.
+pub struct FixedBufPool {
+    inner: Rc<RefCell<PoolInner>>,
+}
+
+struct PoolInner {
+    bufs: Vec<Box<[u8]>>,
+    free: Vec<u16>,
+    reactor: ReactorRef,
+}
+
+impl FixedBufPool {
+    /// Allocate `count` buffers of `size` bytes and register them with the current reactor.
+    pub fn new(count: usize, size: usize) -> IoResult<Self> {
+        if count == 0 || count > u16::MAX as usize + 1 {
+            return Err(IoError::new(ErrorKind::InvalidInput, "invalid fixed buffer count"));
+        }
+
+        let mut bufs: Vec<Box<[u8]>> = (0..count).map(|_| vec![0u8; size].into_boxed_slice()).collect();
+        let iovecs: Vec<libc::iovec> = bufs
+            .iter_mut()
+            .map(|buf| libc::iovec {
+                iov_base: buf.as_mut_ptr() as *mut _,
+                iov_len: buf.len(),
+            })
+            .collect();
+
+        let reactor = current_reactor()?;
+        reactor.borrow_mut().register_buffers(&iovecs).map_err(|e| match e.raw_os_error() {
+            Some(libc::EBUSY) => IoError::new(ErrorKind::AlreadyExists, "another FixedBufPool is already registered"),
+            _ => e,
+        })?;
+
+        Ok(Self {
+            inner: Rc::new(RefCell::new(PoolInner {
+                // hand out low indices first
+                free: (0..count as u16).rev().collect(),
+                bufs,
+                reactor: ReactorRef::bound_to(&reactor),
+            })),
+        })
+    }
+
+    /// Check out a free buffer, `None` if all of them are in use.
+    pub fn try_next(&self) -> Option<FixedBuf> {
+        let mut inner = self.inner.borrow_mut();
+        let index = inner.free.pop()?;
+        let buf = &mut inner.bufs[index as usize];
+
+        Some(FixedBuf {
+            ptr: buf.as_mut_ptr(),
+            capacity: buf.len(),
+            len: 0,
+            index,
+            pool: self.inner.clone(),
+        })
+    }
+
+    /// Number of buffers currently available.
+    pub fn available(&self) -> usize {
+        self.inner.borrow().free.len()
+    }
+}
+
+impl Drop for PoolInner {
+    fn drop(&mut self) {
+        let Some(reactor) = self.reactor.bound() else {
+            return;
+        };
+        // the last buffer may be dropped by the reactor, once the op that owned it completed
+        if let Ok(mut inner) = reactor.try_borrow_mut() {
+            let _ = inner.unregister_buffers();
+            return;
+        }
+        let bufs = std::mem::take(&mut self.bufs);
+        crate::io::detach(async move {
+            let _ = reactor.borrow_mut().unregister_buffers();
+            drop(bufs);
+        });
+    }
+}
+
+/// A buffer checked out of a [`FixedBufPool`], it goes back to the pool on drop.
+///
+/// Derefs to the first `len()` bytes. Reads fill the buffer from the start and set
+/// the length to the number of bytes read, writes send the first `len()` bytes.
+pub struct FixedBuf {
+    ptr: *mut u8,
+    capacity: usize,
+    len: usize,
+    index: u16,
+    // keeps the registered memory alive
+    pool: Rc<RefCell<PoolInner>>,
+}
+
+impl FixedBuf {
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Set the number of valid bytes, the whole buffer is always initialized.
+    pub fn set_len(&mut self, len: usize) {
+        assert!(len <= self.capacity, "len exceeds the buffer capacity");
+        self.len = len;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Replace the content with `data`, returns the number of bytes copied.
+    pub fn put_slice(&mut self, data: &[u8]) -> usize {
+        let n = data.len().min(self.capacity);
+        unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), self.ptr, n) };
+        self.len = n;
+        n
+    }
+
+    pub(crate) fn index(&self) -> u16 {
+        self.index
+    }
+
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut u8 {
+        self.ptr
+    }
+}
+
+impl Deref for FixedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl DerefMut for FixedBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl Drop for FixedBuf {
+    fn drop(&mut self) {
+        self.pool.borrow_mut().free.push(self.index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::fd::AsRawFd, task::Poll};
+
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::{
+        executor::Executor,
+        io::{pipe, AsyncFixedReader},
+    };
+
+    async fn yield_now() {
+        let mut yielded = false;
+        futures::future::poll_fn(|cx| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    #[test]
+    fn pool_dropped_while_a_read_is_in_flight() {
+        Executor::new().block_on(|| async {
+            let (r, _w) = pipe().unwrap();
+            let pool = FixedBufPool::new(1, 16).unwrap();
+            let buf = pool.try_next().unwrap();
+
+            let mut read = AsyncFixedReader::new(r.as_raw_fd(), buf, 0);
+            assert!((&mut read).now_or_never().is_none());
+            drop(read);
+            drop(pool);
+
+            // the kernel may still write into the buffer until the read is cancelled
+            let err = FixedBufPool::new(1, 16).err().unwrap();
+            assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+
+            for _ in 0..8 {
+                yield_now().await;
+            }
+            FixedBufPool::new(1, 16).unwrap();
+        });
+    }
+}
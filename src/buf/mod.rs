@@ -0,0 +1,4 @@
+mod fixed;
+mod ring;
+pub use fixed::*;
+pub use ring::*;
@@ -0,0 +1,171 @@
+use std::{
+    alloc::{alloc_zeroed, dealloc, Layout},
+    cell::Cell,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    ops::Deref,
+    rc::Rc,
+    sync::atomic::{AtomicU16, Ordering},
+};
+
+use io_uring::types::BufRingEntry;
+
+use crate::reactor::{current_reactor, ReactorRef};
+
+const PAGE_SIZE: usize = 4096;
+
+/// A provided buffer ring registered with the ring (`IORING_REGISTER_PBUF_RING`).
+///
+/// Receives issued with `IOSQE_BUFFER_SELECT` let the kernel pick a buffer from the
+/// ring only once data arrives, so idle connections don't keep a buffer pinned.
+/// The picked buffer is handed out as a [`ProvidedBuf`] which goes back to the ring on drop.
+#[derive(Clone)]
+pub struct BufRing {
+    inner: Rc<RingInner>,
+}
+
+struct RingInner {
+    bgid: u16,
+    entries: u16,
+    // shared with the kernel, the kernel consumes entries and we bump the tail
+    ring: *mut BufRingEntry,
+    ring_layout: Layout,
+    // `entries` buffers of `buf_len` bytes each, buffer id == index
+    bufs: *mut u8,
+    bufs_layout: Layout,
+    buf_len: usize,
+    tail: Cell<u16>,
+    reactor: ReactorRef,
+}
+
+impl BufRing {
+    /// Allocate `entries` buffers of `buf_len` bytes and register them as a new buffer group.
+    ///
+    /// `entries` must be a power of two, up to 32768.
+    pub fn new(entries: u16, buf_len: usize) -> IoResult<Self> {
+        if !entries.is_power_of_two() || entries > 1 << 15 {
+            return Err(IoError::new(ErrorKind::InvalidInput, "entries must be a power of two up to 32768"));
+        }
+        if buf_len == 0 || buf_len > u32::MAX as usize {
+            return Err(IoError::new(ErrorKind::InvalidInput, "invalid buffer length"));
+        }
+
+        let ring_layout = Layout::from_size_align(entries as usize * std::mem::size_of::<BufRingEntry>(), PAGE_SIZE)
+            .map_err(|e| IoError::new(ErrorKind::InvalidInput, e))?;
+        let bufs_layout = Layout::from_size_align(entries as usize * buf_len, 64)
+            .map_err(|e| IoError::new(ErrorKind::InvalidInput, e))?;
+
+        let ring = unsafe { alloc_zeroed(ring_layout) } as *mut BufRingEntry;
+        let bufs = unsafe { alloc_zeroed(bufs_layout) };
+        if ring.is_null() || bufs.is_null() {
+            std::alloc::handle_alloc_error(ring_layout);
+        }
+
+        let reactor = current_reactor()?;
+        let bgid = match unsafe { reactor.borrow_mut().register_buf_ring(ring as u64, entries) } {
+            Ok(bgid) => bgid,
+            Err(e) => {
+                unsafe {
+                    dealloc(ring as *mut u8, ring_layout);
+                    dealloc(bufs, bufs_layout);
+                }
+                return Err(e);
+            }
+        };
+
+        let inner = RingInner {
+            bgid,
+            entries,
+            ring,
+            ring_layout,
+            bufs,
+            bufs_layout,
+            buf_len,
+            tail: Cell::new(0),
+            reactor: ReactorRef::bound_to(&reactor),
+        };
+        for bid in 0..entries {
+            inner.push(bid);
+        }
+
+        Ok(Self { inner: Rc::new(inner) })
+    }
+
+    pub fn buf_len(&self) -> usize {
+        self.inner.buf_len
+    }
+
+    pub(crate) fn bgid(&self) -> u16 {
+        self.inner.bgid
+    }
+
+    /// Take ownership of the buffer `bid` the kernel filled with `len` bytes.
+    pub(crate) fn take(&self, bid: u16, len: usize) -> ProvidedBuf {
+        assert!(bid < self.inner.entries && len <= self.inner.buf_len);
+        ProvidedBuf {
+            ring: self.inner.clone(),
+            bid,
+            len,
+        }
+    }
+}
+
+impl RingInner {
+    /// Hand buffer `bid` (back) to the kernel.
+    fn push(&self, bid: u16) {
+        let tail = self.tail.get();
+        let mask = self.entries - 1;
+        unsafe {
+            let entry = &mut *self.ring.add((tail & mask) as usize);
+            entry.set_addr(self.bufs.add(bid as usize * self.buf_len) as u64);
+            entry.set_len(self.buf_len as u32);
+            entry.set_bid(bid);
+
+            let tail_ptr = BufRingEntry::tail(self.ring) as *mut u16;
+            AtomicU16::from_ptr(tail_ptr).store(tail.wrapping_add(1), Ordering::Release);
+        }
+        self.tail.set(tail.wrapping_add(1));
+    }
+}
+
+impl Drop for RingInner {
+    fn drop(&mut self) {
+        if let Some(reactor) = self.reactor.bound() {
+            let _ = reactor.borrow_mut().unregister_buf_ring(self.bgid);
+        }
+        unsafe {
+            dealloc(self.ring as *mut u8, self.ring_layout);
+            dealloc(self.bufs, self.bufs_layout);
+        }
+    }
+}
+
+/// A buffer the kernel picked from a [`BufRing`], it goes back to the ring on drop.
+pub struct ProvidedBuf {
+    ring: Rc<RingInner>,
+    bid: u16,
+    len: usize,
+}
+
+impl ProvidedBuf {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Deref for ProvidedBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ring.bufs.add(self.bid as usize * self.ring.buf_len), self.len) }
+    }
+}
+
+impl Drop for ProvidedBuf {
+    fn drop(&mut self) {
+        self.ring.push(self.bid);
+    }
+}
@@ -0,0 +1,54 @@
+use std::io;
+
+use bytes::{Bytes, BytesMut};
+
+use super::{Decoder, Encoder};
+
+/// Passes bytes through as they come, in chunks of whatever each read returned.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BytesCodec;
+
+impl BytesCodec {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Decoder for BytesCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        if src.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(src.split()))
+        }
+    }
+}
+
+impl Encoder<Bytes> for BytesCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> io::Result<()> {
+        dst.extend_from_slice(&item);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passes_bytes_through() {
+        let mut codec = BytesCodec::new();
+        let mut buf = BytesMut::new();
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+
+        codec.encode(Bytes::from_static(b"abc"), &mut buf).unwrap();
+        codec.encode(Bytes::from_static(b"de"), &mut buf).unwrap();
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], b"abcde");
+        assert_eq!(codec.decode_eof(&mut buf).unwrap(), None);
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    io,
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use bytes::BytesMut;
+use futures::{future::LocalBoxFuture, FutureExt, Sink, Stream};
+
+use crate::io::{detach, AsyncRead, AsyncWrite, AsyncWriteExt};
+
+use super::{Decoder, Encoder};
+
+const READ_SIZE: usize = 8 * 1024;
+// encoded bytes after which `poll_ready` writes them out first
+const BACKPRESSURE: usize = 64 * 1024;
+
+/// A `Stream` of the frames `C` decodes from `T`, and a `Sink` of the items it encodes into it.
+///
+/// Reads and writes go through buffers the adapter owns. The `Stream` needs `T: AsyncRead`
+/// and the `Sink` `T: AsyncWrite`. Items are only written when the sink is flushed, which
+/// `SinkExt::send` does. The stream ends after the first error.
+pub struct Framed<T, C> {
+    inner: Rc<T>,
+    codec: C,
+    read_buf: BytesMut,
+    read_op: Option<LocalBoxFuture<'static, (io::Result<usize>, Vec<u8>)>>,
+    eof: bool,
+    done: bool,
+    write_buf: BytesMut,
+    write_op: Option<LocalBoxFuture<'static, io::Result<()>>>,
+    shutdown_op: Option<LocalBoxFuture<'static, io::Result<()>>>,
+}
+
+impl<T: 'static, C> Framed<T, C> {
+    pub fn new(inner: T, codec: C) -> Self {
+        Self {
+            inner: Rc::new(inner),
+            codec,
+            read_buf: BytesMut::new(),
+            read_op: None,
+            eof: false,
+            done: false,
+            write_buf: BytesMut::new(),
+            write_op: None,
+            shutdown_op: None,
+        }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    pub fn codec_mut(&mut self) -> &mut C {
+        &mut self.codec
+    }
+
+    /// Bytes read but not decoded yet.
+    pub fn read_buffer(&self) -> &BytesMut {
+        &self.read_buf
+    }
+}
+
+impl<T: AsyncRead + 'static, C: Decoder> Framed<T, C> {
+    fn decode_next(&mut self) -> Option<Result<C::Item, C::Error>> {
+        let decoded = if self.eof {
+            self.codec.decode_eof(&mut self.read_buf)
+        } else {
+            self.codec.decode(&mut self.read_buf)
+        };
+        match decoded {
+            Ok(Some(frame)) => Some(Ok(frame)),
+            Ok(None) => {
+                self.done = self.eof;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+
+    fn poll_read_more(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.read_op.is_none() {
+            let inner = self.inner.clone();
+            self.read_op = Some(
+                async move {
+                    let mut buf = vec![0; READ_SIZE];
+                    let result = inner.read(&mut buf).await;
+                    (result, buf)
+                }
+                .boxed_local(),
+            );
+        }
+
+        let (result, buf) = futures::ready!(self.read_op.as_mut().unwrap().poll_unpin(cx));
+        self.read_op = None;
+        match result? {
+            0 => self.eof = true,
+            n => self.read_buf.extend_from_slice(&buf[..n]),
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: AsyncRead + 'static, C: Decoder + Unpin> Stream for Framed<T, C> {
+    type Item = Result<C::Item, C::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.done {
+                return Poll::Ready(None);
+            }
+            if let Some(frame) = this.decode_next() {
+                return Poll::Ready(Some(frame));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            if let Err(e) = futures::ready!(this.poll_read_more(cx)) {
+                this.done = true;
+                return Poll::Ready(Some(Err(e.into())));
+            }
+        }
+    }
+}
+
+impl<T: AsyncWrite + 'static, C> Framed<T, C> {
+    /// Drive the write of what is encoded, then flush `T`.
+    fn poll_write_out(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.write_op.is_none() {
+            if self.write_buf.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            let inner = self.inner.clone();
+            let data = self.write_buf.split();
+            self.write_op = Some(
+                async move {
+                    inner.write_all(&data).await?;
+                    inner.flush().await
+                }
+                .boxed_local(),
+            );
+        }
+
+        let result = futures::ready!(self.write_op.as_mut().unwrap().poll_unpin(cx));
+        self.write_op = None;
+
+        Poll::Ready(result)
+    }
+}
+
+impl<T: AsyncWrite + 'static, I, C: Encoder<I> + Unpin> Sink<I> for Framed<T, C> {
+    type Error = C::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if this.write_op.is_some() || this.write_buf.len() >= BACKPRESSURE {
+            futures::ready!(this.poll_write_out(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: I) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        this.codec.encode(item, &mut this.write_buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        // a write may have been in flight when more was encoded
+        while this.write_op.is_some() || !this.write_buf.is_empty() {
+            futures::ready!(this.poll_write_out(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        futures::ready!(self.as_mut().poll_flush(cx))?;
+
+        let this = self.get_mut();
+        if this.shutdown_op.is_none() {
+            let inner = this.inner.clone();
+            this.shutdown_op = Some(async move { inner.shutdown().await }.boxed_local());
+        }
+        let result = futures::ready!(this.shutdown_op.as_mut().unwrap().poll_unpin(cx));
+        this.shutdown_op = None;
+
+        Poll::Ready(result.map_err(Into::into))
+    }
+}
+
+impl<T, C> Drop for Framed<T, C> {
+    fn drop(&mut self) {
+        if let Some(op) = self.read_op.take() {
+            detach(op);
+        }
+        if let Some(op) = self.write_op.take() {
+            detach(op);
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::io;
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use super::{Decoder, Encoder};
+
+const HEADER_LEN: usize = 4;
+
+/// Frames prefixed with their length as a big-endian `u32`.
+#[derive(Clone, Debug)]
+pub struct LengthDelimitedCodec {
+    max_frame_length: usize,
+}
+
+impl Default for LengthDelimitedCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LengthDelimitedCodec {
+    /// Frames up to 8 MiB.
+    pub fn new() -> Self {
+        Self::with_max_frame_length(8 * 1024 * 1024)
+    }
+
+    /// Fail with `InvalidData` on frames longer than `max_frame_length`, in either direction.
+    pub fn with_max_frame_length(max_frame_length: usize) -> Self {
+        Self {
+            max_frame_length: max_frame_length.min(u32::MAX as usize),
+        }
+    }
+
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+
+    fn too_long(&self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame longer than {} bytes", self.max_frame_length),
+        )
+    }
+}
+
+impl Decoder for LengthDelimitedCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        if src.len() < HEADER_LEN {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes(src[..HEADER_LEN].try_into().unwrap()) as usize;
+        if len > self.max_frame_length {
+            return Err(self.too_long());
+        }
+        if src.len() < HEADER_LEN + len {
+            // make room for the rest of the frame at once
+            src.reserve(HEADER_LEN + len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(HEADER_LEN);
+        Ok(Some(src.split_to(len)))
+    }
+}
+
+impl Encoder<Bytes> for LengthDelimitedCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: Bytes, dst: &mut BytesMut) -> io::Result<()> {
+        if frame.len() > self.max_frame_length {
+            return Err(self.too_long());
+        }
+        dst.reserve(HEADER_LEN + frame.len());
+        dst.put_u32(frame.len() as u32);
+        dst.extend_from_slice(&frame);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut codec = LengthDelimitedCodec::new();
+        let mut buf = BytesMut::new();
+        for frame in [&b"a"[..], b"", b"hello"] {
+            codec.encode(Bytes::copy_from_slice(frame), &mut buf).unwrap();
+        }
+        assert_eq!(&buf[..5], &[0, 0, 0, 1, b'a']);
+
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], b"a");
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], b"");
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], b"hello");
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+    }
+
+    #[test]
+    fn waits_for_the_whole_frame() {
+        let mut codec = LengthDelimitedCodec::new();
+        let mut buf = BytesMut::from(&[0, 0][..]);
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        buf.extend_from_slice(&[0, 3, b'a', b'b']);
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        buf.extend_from_slice(b"c");
+        assert_eq!(&codec.decode(&mut buf).unwrap().unwrap()[..], b"abc");
+        assert!(codec.decode_eof(&mut buf).unwrap().is_none());
+    }
+
+    #[test]
+    fn truncated_frame_at_eof() {
+        let mut codec = LengthDelimitedCodec::new();
+        let mut buf = BytesMut::from(&[0, 0, 0, 3, b'a'][..]);
+        assert_eq!(codec.decode_eof(&mut buf).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn max_frame_length() {
+        let mut codec = LengthDelimitedCodec::with_max_frame_length(4);
+        let mut buf = BytesMut::from(&[0, 0, 0, 5][..]);
+        assert_eq!(codec.decode(&mut buf).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut dst = BytesMut::new();
+        let err = codec.encode(Bytes::from_static(b"12345"), &mut dst).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(dst.is_empty());
+        codec.encode(Bytes::from_static(b"1234"), &mut dst).unwrap();
+        assert_eq!(dst.len(), 8);
+    }
+}
@@ -0,0 +1,177 @@
+use std::io;
+
+use bytes::{Buf, BytesMut};
+
+use super::{Decoder, Encoder};
+
+/// Lines ending with `\n` or `\r\n`, decoded to `String`s without the line ending.
+///
+/// A last line without an ending is still yielded at end of stream.
+#[derive(Clone, Debug)]
+pub struct LinesCodec {
+    max_length: usize,
+    // where to resume looking for `\n`, so a long line isn't scanned again on every read
+    next_index: usize,
+}
+
+impl Default for LinesCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinesCodec {
+    pub fn new() -> Self {
+        Self::with_max_length(usize::MAX)
+    }
+
+    /// Fail with `InvalidData` on lines longer than `max_length` bytes, rather than buffering
+    /// whatever a peer sends without a line ending.
+    pub fn with_max_length(max_length: usize) -> Self {
+        Self {
+            max_length,
+            next_index: 0,
+        }
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    fn take_line(&mut self, src: &mut BytesMut, len: usize) -> io::Result<String> {
+        self.next_index = 0;
+        let mut line = src.split_to(len);
+        if line.ends_with(b"\n") {
+            line.truncate(line.len() - 1);
+            if line.ends_with(b"\r") {
+                line.truncate(line.len() - 1);
+            }
+        }
+        String::from_utf8(line.to_vec()).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "line is not valid UTF-8"))
+    }
+}
+
+impl Decoder for LinesCodec {
+    type Item = String;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<String>> {
+        match src[self.next_index..].iter().position(|&b| b == b'\n') {
+            Some(i) => {
+                let len = self.next_index + i + 1;
+                if line_len(&src[..len - 1]) > self.max_length {
+                    self.next_index = 0;
+                    src.advance(len);
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"));
+                }
+                self.take_line(src, len).map(Some)
+            }
+            None if line_len(src) > self.max_length => {
+                self.next_index = 0;
+                src.advance(src.len());
+                Err(io::Error::new(io::ErrorKind::InvalidData, "line too long"))
+            }
+            None => {
+                self.next_index = src.len();
+                Ok(None)
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<String>> {
+        match self.decode(src)? {
+            Some(line) => Ok(Some(line)),
+            None if src.is_empty() => Ok(None),
+            None => self.take_line(src, src.len()).map(Some),
+        }
+    }
+}
+
+// length of a line without its ending, a trailing `\r` may still be followed by `\n`
+fn line_len(line: &[u8]) -> usize {
+    line.strip_suffix(b"\r").unwrap_or(line).len()
+}
+
+// a single item type, so `SinkExt::close` needs no annotation
+impl Encoder<String> for LinesCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, line: String, dst: &mut BytesMut) -> io::Result<()> {
+        dst.reserve(line.len() + 1);
+        dst.extend_from_slice(line.as_bytes());
+        dst.extend_from_slice(b"\n");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_lines_across_reads() {
+        let mut codec = LinesCodec::new();
+        let mut src = BytesMut::from("hel");
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        src.extend_from_slice(b"lo\r\nworld\nlast");
+        assert_eq!(codec.decode(&mut src).unwrap().as_deref(), Some("hello"));
+        assert_eq!(codec.decode(&mut src).unwrap().as_deref(), Some("world"));
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        assert_eq!(codec.decode_eof(&mut src).unwrap().as_deref(), Some("last"));
+        assert_eq!(codec.decode_eof(&mut src).unwrap(), None);
+    }
+
+    #[test]
+    fn too_long_without_newline_resets() {
+        let mut codec = LinesCodec::with_max_length(4);
+        let mut src = BytesMut::from("abcd");
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        src.extend_from_slice(b"e");
+        assert_eq!(codec.decode(&mut src).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(src.is_empty());
+        src.extend_from_slice(b"\n");
+        assert_eq!(codec.decode(&mut src).unwrap().as_deref(), Some(""));
+    }
+
+    #[test]
+    fn too_long_with_newline_is_discarded() {
+        let mut codec = LinesCodec::with_max_length(4);
+        let mut src = BytesMut::from("abc");
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        src.extend_from_slice(b"def\nok\n");
+        assert_eq!(codec.decode(&mut src).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(codec.decode(&mut src).unwrap().as_deref(), Some("ok"));
+    }
+
+    #[test]
+    fn max_length_is_inclusive() {
+        let mut codec = LinesCodec::with_max_length(4);
+        let mut src = BytesMut::from("abcd\n");
+        assert_eq!(codec.decode(&mut src).unwrap().as_deref(), Some("abcd"));
+    }
+
+    #[test]
+    fn max_length_excludes_crlf() {
+        let mut codec = LinesCodec::with_max_length(4);
+        let mut src = BytesMut::from("abcd\r");
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+        src.extend_from_slice(b"\nabcde\r\n");
+        assert_eq!(codec.decode(&mut src).unwrap().as_deref(), Some("abcd"));
+        assert_eq!(codec.decode(&mut src).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let mut codec = LinesCodec::new();
+        let mut src = BytesMut::from(&b"\xff\n"[..]);
+        assert_eq!(codec.decode(&mut src).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn encodes_with_newline() {
+        let mut dst = BytesMut::new();
+        LinesCodec::new().encode("hi".to_string(), &mut dst).unwrap();
+        assert_eq!(&dst[..], b"hi\n");
+    }
+}
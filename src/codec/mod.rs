@@ -0,0 +1,49 @@
+//! Framing of byte streams: [`Framed`] turns a stream into a `Stream` of decoded frames and
+//! a `Sink` of items to encode.
+//!
+//! ```ignore
+//! let mut lines = Framed::new(stream, LinesCodec::new());
+//! while let Some(line) = lines.next().await {
+//!     lines.send(line?).await?;
+//! }
+//! ```
+
+use std::io;
+
+mod bytes_codec;
+mod framed;
+mod length_delimited;
+mod lines;
+
+pub use bytes::{Bytes, BytesMut};
+pub use bytes_codec::BytesCodec;
+pub use framed::Framed;
+pub use length_delimited::LengthDelimitedCodec;
+pub use lines::LinesCodec;
+
+/// Cuts frames out of the bytes read so far.
+pub trait Decoder {
+    type Item;
+    type Error: From<io::Error>;
+
+    /// Take the next frame from the front of `src`, `None` if it isn't complete yet.
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error>;
+
+    /// Like [`decode`](Self::decode) once the end of the stream has been reached, called
+    /// until it returns `None`. Fails by default if bytes are left over.
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(src)? {
+            Some(frame) => Ok(Some(frame)),
+            None if src.is_empty() => Ok(None),
+            None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "bytes remaining on stream").into()),
+        }
+    }
+}
+
+/// Turns items into bytes to write.
+pub trait Encoder<Item> {
+    type Error: From<io::Error>;
+
+    /// Append the encoding of `item` to `dst`.
+    fn encode(&mut self, item: Item, dst: &mut BytesMut) -> Result<(), Self::Error>;
+}
@@ -0,0 +1,157 @@
+use std::{fmt, io};
+
+use io_uring::{opcode, IoUring, Probe};
+
+use super::DriverKind;
+
+/// What the driver and the host kernel support, probed once when the executor starts.
+///
+/// Features without an opcode of their own are inferred from an opcode added in the same
+/// kernel release, which errs on the side of reporting them missing.
+#[derive(Clone)]
+pub struct Capabilities {
+    driver: DriverKind,
+    // indexed by IORING_OP_* code
+    opcodes: [bool; 256],
+    features: Vec<&'static str>,
+    setup_flags: Vec<&'static str>,
+}
+
+impl Capabilities {
+    pub(crate) fn epoll() -> Self {
+        Self {
+            driver: DriverKind::Epoll,
+            opcodes: [false; 256],
+            features: Vec::new(),
+            setup_flags: Vec::new(),
+        }
+    }
+
+    pub(crate) fn probe(uring: &IoUring) -> Self {
+        let mut probe = Probe::new();
+        let mut opcodes = [false; 256];
+        // kernels before 5.6 can't be probed, they only get the basics
+        if uring.submitter().register_probe(&mut probe).is_ok() {
+            for (code, supported) in opcodes.iter_mut().enumerate() {
+                *supported = probe.is_supported(code as u8);
+            }
+        }
+
+        let params = uring.params();
+        let features = [
+            ("single_mmap", params.is_feature_single_mmap()),
+            ("nodrop", params.is_feature_nodrop()),
+            ("submit_stable", params.is_feature_submit_stable()),
+            ("rw_cur_pos", params.is_feature_rw_cur_pos()),
+            ("cur_personality", params.is_feature_cur_personality()),
+            ("fast_poll", params.is_feature_fast_poll()),
+            ("poll_32bits", params.is_feature_poll_32bits()),
+            ("sqpoll_nonfixed", params.is_feature_sqpoll_nonfixed()),
+            ("ext_arg", params.is_feature_ext_arg()),
+            ("native_workers", params.is_feature_native_workers()),
+            ("resource_tagging", params.is_feature_resource_tagging()),
+            ("skip_cqe_on_success", params.is_feature_skip_cqe_on_success()),
+            ("linked_file", params.is_feature_linked_file()),
+        ];
+        let setup_flags = [
+            ("sqpoll", params.is_setup_sqpoll()),
+            ("iopoll", params.is_setup_iopoll()),
+            ("single_issuer", params.is_setup_single_issuer()),
+        ];
+
+        Self {
+            driver: DriverKind::IoUring,
+            opcodes,
+            features: features.iter().filter(|(_, on)| *on).map(|(name, _)| *name).collect(),
+            setup_flags: setup_flags.iter().filter(|(_, on)| *on).map(|(name, _)| *name).collect(),
+        }
+    }
+
+    pub fn driver(&self) -> DriverKind {
+        self.driver
+    }
+
+    /// Whether the kernel knows the opcode, e.g. `io_uring::opcode::SendZc::CODE`.
+    pub fn supports_opcode(&self, code: u8) -> bool {
+        self.opcodes[code as usize]
+    }
+
+    /// `IORING_FEAT_*` flags reported by the kernel, e.g. `"fast_poll"`.
+    pub fn features(&self) -> &[&'static str] {
+        &self.features
+    }
+
+    /// `IORING_SETUP_*` flags the ring was created with.
+    pub fn setup_flags(&self) -> &[&'static str] {
+        &self.setup_flags
+    }
+
+    /// Multishot poll, Linux 5.13.
+    pub fn multishot_poll(&self) -> bool {
+        // IORING_OP_MKDIRAT, 5.15
+        self.supports_opcode(opcode::MkDirAt::CODE)
+    }
+
+    /// Multishot accept, Linux 5.19.
+    pub fn multishot_accept(&self) -> bool {
+        // IORING_OP_SOCKET, 5.19
+        self.supports_opcode(opcode::Socket::CODE)
+    }
+
+    /// Multishot receive, Linux 6.0.
+    pub fn multishot_recv(&self) -> bool {
+        // IORING_OP_SEND_ZC, 6.0
+        self.supports_opcode(opcode::SendZc::CODE)
+    }
+
+    /// Provided buffer rings, Linux 5.19.
+    pub fn provided_buffers(&self) -> bool {
+        self.supports_opcode(opcode::Socket::CODE)
+    }
+
+    /// A sparse fixed file table, needed for registered fds, Linux 5.19.
+    pub fn fixed_files(&self) -> bool {
+        self.supports_opcode(opcode::Socket::CODE)
+    }
+
+    /// Opening and accepting straight into the fixed file table, Linux 5.15.
+    pub fn direct_descriptors(&self) -> bool {
+        // IORING_OP_MKDIRAT, 5.15, and they live in the fixed file table
+        self.supports_opcode(opcode::MkDirAt::CODE) && self.fixed_files()
+    }
+
+    /// Zero-copy sends, Linux 6.0.
+    pub fn send_zc(&self) -> bool {
+        self.supports_opcode(opcode::SendZc::CODE)
+    }
+
+    /// Fail with `ErrorKind::Unsupported` naming `feature` unless it is `supported`.
+    pub(crate) fn require(&self, supported: bool, feature: &str) -> io::Result<()> {
+        if supported {
+            return Ok(());
+        }
+        let msg = match self.driver {
+            DriverKind::Epoll => format!("{feature}: needs the io_uring driver"),
+            DriverKind::IoUring => format!("{feature}: not supported by this kernel"),
+        };
+        Err(io::Error::new(io::ErrorKind::Unsupported, msg))
+    }
+}
+
+impl fmt::Debug for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Capabilities")
+            .field("driver", &self.driver)
+            .field("opcodes", &self.opcodes.iter().filter(|supported| **supported).count())
+            .field("multishot_poll", &self.multishot_poll())
+            .field("multishot_accept", &self.multishot_accept())
+            .field("multishot_recv", &self.multishot_recv())
+            .field("provided_buffers", &self.provided_buffers())
+            .field("fixed_files", &self.fixed_files())
+            .field("direct_descriptors", &self.direct_descriptors())
+            .field("send_zc", &self.send_zc())
+            .field("features", &self.features)
+            .field("setup_flags", &self.setup_flags)
+            .finish()
+    }
+}
@@ -0,0 +1,365 @@
+use std::{
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+};
+
+use crate::reactor::FdTarget;
+
+use super::{Capabilities, Driver, DriverKind, Op};
+
+/// Emulates the completion model on top of level-triggered epoll: an operation waits for its fd
+/// to be ready, then the syscall runs and its result is reported like a CQE would be.
+///
+/// Files epoll refuses (regular files, block devices) are always ready, so their operations
+/// run right away.
+///
+/// The fds it reads, writes or accepts on are switched to non-blocking mode, so an fd drained by
+/// an earlier operation, a connection taken by another process or a write larger than the free
+/// space never block the thread. `EAGAIN` puts the operation back to waiting.
+pub(crate) struct EpollDriver {
+    epfd: OwnedFd,
+    // operations waiting for readiness
+    pending: rustc_hash::FxHashMap<u64, Pending>,
+    fds: rustc_hash::FxHashMap<RawFd, FdState>,
+    // completions to report on the next `wait`
+    done: Vec<(u64, i32, u32)>,
+    events: Vec<libc::epoll_event>,
+}
+
+struct Pending {
+    fd: RawFd,
+    op: Op,
+}
+
+#[derive(Default)]
+struct FdState {
+    // in submission order
+    tokens: Vec<u64>,
+    // events the fd is registered with, 0 when it isn't
+    registered: u32,
+}
+
+impl EpollDriver {
+    pub(crate) fn new() -> io::Result<Self> {
+        let epfd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if epfd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            epfd: unsafe { OwnedFd::from_raw_fd(epfd) },
+            pending: Default::default(),
+            fds: Default::default(),
+            done: Vec::new(),
+            events: Vec::with_capacity(128),
+        })
+    }
+
+    /// Point the epoll registration of `fd` at the events its pending operations wait for.
+    fn update_interest(&mut self, fd: RawFd) -> io::Result<()> {
+        let state = self.fds.entry(fd).or_default();
+        let interest = state
+            .tokens
+            .iter()
+            .fold(0, |interest, token| interest | self.pending[token].op.interest());
+        if interest == state.registered {
+            return Ok(());
+        }
+
+        let (ctl, events) = match (state.registered, interest) {
+            (_, 0) => (libc::EPOLL_CTL_DEL, 0),
+            (0, _) => (libc::EPOLL_CTL_ADD, interest),
+            _ => (libc::EPOLL_CTL_MOD, interest),
+        };
+        let mut event = libc::epoll_event {
+            events,
+            u64: fd as u64,
+        };
+        let ret = unsafe { libc::epoll_ctl(self.epfd.as_raw_fd(), ctl, fd, &mut event) };
+        if ret < 0 && ctl != libc::EPOLL_CTL_DEL {
+            return Err(io::Error::last_os_error());
+        }
+
+        state.registered = interest;
+        if interest == 0 {
+            self.fds.remove(&fd);
+        }
+        Ok(())
+    }
+
+    /// Run the operations of `fd` that `revents` made ready.
+    fn process(&mut self, fd: RawFd, revents: u32) {
+        let Some(state) = self.fds.get_mut(&fd) else {
+            return;
+        };
+        let tokens = std::mem::take(&mut state.tokens);
+        let mut waiting = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            let pending = &self.pending[&token];
+            let interest = pending.op.interest();
+            let failed = revents & (libc::EPOLLERR | libc::EPOLLHUP) as u32 != 0;
+            if revents & interest == 0 && !failed {
+                waiting.push(token);
+                continue;
+            }
+
+            match pending.op.run(fd, revents) {
+                // spurious wakeup, or drained by an operation before this one
+                Some(result) if result == -libc::EAGAIN => waiting.push(token),
+                Some(result) => {
+                    self.pending.remove(&token);
+                    self.done.push((token, result, 0));
+                }
+                None => waiting.push(token),
+            }
+        }
+
+        if let Some(state) = self.fds.get_mut(&fd) {
+            state.tokens = waiting;
+        }
+        let _ = self.update_interest(fd);
+    }
+
+    /// Complete every operation still waiting on `fd` with `ECANCELED`, before it gets closed.
+    fn cancel_fd(&mut self, fd: RawFd) {
+        if let Some(state) = self.fds.remove(&fd) {
+            for token in state.tokens {
+                self.pending.remove(&token);
+                self.done.push((token, -libc::ECANCELED, 0));
+            }
+            if state.registered != 0 {
+                unsafe { libc::epoll_ctl(self.epfd.as_raw_fd(), libc::EPOLL_CTL_DEL, fd, std::ptr::null_mut()) };
+            }
+        }
+    }
+}
+
+impl Op {
+    /// Epoll events the operation waits for, 0 if it doesn't wait.
+    fn interest(&self) -> u32 {
+        match self {
+            Op::Read { .. } | Op::Readv { .. } | Op::Accept { .. } => libc::EPOLLIN as u32,
+            Op::Write { .. } | Op::Writev { .. } => libc::EPOLLOUT as u32,
+            // the POLL* and EPOLL* bits are the same
+            Op::Poll { mask, .. } => *mask,
+            _ => 0,
+        }
+    }
+
+    /// Run the syscall against `fd` given the ready events, `None` if the events don't satisfy a poll.
+    fn run(&self, fd: RawFd, revents: u32) -> Option<i32> {
+        let ret = unsafe {
+            match *self {
+                Op::Read { buf, len, offset, .. } if offset != u64::MAX && is_seekable(fd) => {
+                    libc::pread(fd, buf as *mut _, len as usize, offset as libc::off_t) as i64
+                }
+                Op::Read { buf, len, .. } => libc::read(fd, buf as *mut _, len as usize) as i64,
+                Op::Write { buf, len, offset, .. } if offset != u64::MAX && is_seekable(fd) => {
+                    libc::pwrite(fd, buf as *const _, len as usize, offset as libc::off_t) as i64
+                }
+                Op::Write { buf, len, .. } => libc::write(fd, buf as *const _, len as usize) as i64,
+                Op::Readv { iovecs, len, .. } => libc::readv(fd, iovecs, len as i32) as i64,
+                Op::Writev { iovecs, len, .. } => libc::writev(fd, iovecs, len as i32) as i64,
+                Op::Fsync { .. } => libc::fsync(fd) as i64,
+                Op::Close { .. } => libc::close(fd) as i64,
+                Op::Accept { addr, addr_len, .. } => {
+                    libc::accept4(fd, addr, addr_len, libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK) as i64
+                }
+                Op::Poll { mask, .. } => {
+                    let ready = revents & (mask | (libc::POLLERR | libc::POLLHUP) as u32);
+                    return (ready != 0).then_some(ready as i32);
+                }
+                Op::Uring(_) => return Some(-libc::EOPNOTSUPP),
+            }
+        };
+
+        Some(if ret < 0 { -io::Error::last_os_error().raw_os_error().unwrap_or(libc::EIO) } else { ret as i32 })
+    }
+}
+
+fn set_nonblocking(fd: RawFd) {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags >= 0 && flags & libc::O_NONBLOCK == 0 {
+            libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+        }
+    }
+}
+
+fn is_seekable(fd: RawFd) -> bool {
+    unsafe { libc::lseek(fd, 0, libc::SEEK_CUR) >= 0 }
+}
+
+impl Driver for EpollDriver {
+    fn kind(&self) -> DriverKind {
+        DriverKind::Epoll
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::epoll()
+    }
+
+    fn push(&mut self, token: u64, op: Op) {
+        let fd = match op {
+            Op::Uring(_) => return self.done.push((token, -libc::EOPNOTSUPP, 0)),
+            Op::Read { fd, .. }
+            | Op::Write { fd, .. }
+            | Op::Readv { fd, .. }
+            | Op::Writev { fd, .. }
+            | Op::Fsync { fd }
+            | Op::Close { fd }
+            | Op::Accept { fd, .. }
+            | Op::Poll { fd, .. } => match fd {
+                FdTarget::Raw(fd) => fd,
+                // there is no fixed file table to resolve it
+                FdTarget::Fixed(_) => return self.done.push((token, -libc::EOPNOTSUPP, 0)),
+            },
+        };
+
+        if let Op::Close { .. } = op {
+            self.cancel_fd(fd);
+        }
+        if op.interest() == 0 {
+            let result = op.run(fd, 0).unwrap();
+            self.done.push((token, result, 0));
+            return;
+        }
+
+        // the readiness may be gone by the time the syscall runs, polls leave the I/O to their owner
+        if !matches!(op, Op::Poll { .. }) {
+            set_nonblocking(fd);
+        }
+        self.pending.insert(token, Pending { fd, op });
+        self.fds.entry(fd).or_default().tokens.push(token);
+        if let Err(e) = self.update_interest(fd) {
+            let Pending { op, .. } = self.pending.remove(&token).unwrap();
+            self.fds.get_mut(&fd).unwrap().tokens.retain(|&t| t != token);
+            if self.fds[&fd].tokens.is_empty() {
+                self.fds.remove(&fd);
+            }
+
+            let result = match e.raw_os_error() {
+                // regular files can't be polled, they are always ready
+                Some(libc::EPERM) => op.run(fd, op.interest()).unwrap(),
+                _ => -e.raw_os_error().unwrap_or(libc::EIO),
+            };
+            self.done.push((token, result, 0));
+        }
+    }
+
+    fn push_link(&mut self, _ops: Vec<(u64, Op)>, _hard: bool) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "linked operations need the io_uring driver"))
+    }
+
+    fn cancel(&mut self, token: u64) {
+        if let Some(Pending { fd, .. }) = self.pending.remove(&token) {
+            if let Some(state) = self.fds.get_mut(&fd) {
+                state.tokens.retain(|&t| t != token);
+            }
+            let _ = self.update_interest(fd);
+            self.done.push((token, -libc::ECANCELED, 0));
+        }
+    }
+
+    fn submit(&mut self) {}
+
+    fn wait(&mut self, completions: &mut Vec<(u64, i32, u32)>) {
+        // like the io_uring driver, only reap what is already there
+        let n = unsafe {
+            libc::epoll_wait(self.epfd.as_raw_fd(), self.events.as_mut_ptr(), self.events.capacity() as i32, 0)
+        };
+        if n > 0 {
+            unsafe { self.events.set_len(n as usize) };
+            let events = std::mem::take(&mut self.events);
+            for event in &events {
+                let (fd, revents) = (event.u64 as RawFd, event.events);
+                self.process(fd, revents);
+            }
+            self.events = events;
+            self.events.clear();
+        }
+
+        completions.append(&mut self.done);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        net::TcpStream,
+        sync::mpsc::{self, RecvTimeoutError},
+        thread,
+        time::Duration,
+    };
+
+    use crate::{
+        executor::{DriverKind, Executor},
+        io::{pipe, AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    // fails instead of hanging if the driver blocks the thread
+    fn on_epoll<F, T>(f: F)
+    where
+        F: Fn() -> T + Send + 'static,
+        T: Future<Output = ()> + 'static,
+    {
+        let (done, finished) = mpsc::channel();
+        thread::spawn(move || {
+            Executor::with_driver(DriverKind::Epoll).unwrap().block_on(f);
+            let _ = done.send(());
+        });
+        match finished.recv_timeout(Duration::from_secs(10)) {
+            Ok(()) => {}
+            Err(RecvTimeoutError::Timeout) => panic!("the epoll driver blocked the thread"),
+            Err(RecvTimeoutError::Disconnected) => panic!("the test panicked"),
+        }
+    }
+
+    #[test]
+    fn accept_taken_by_another_fd() {
+        on_epoll(|| async {
+            let std_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = std_listener.local_addr().unwrap();
+            // two fds on the same listening socket, both are reported ready for one connection
+            let first = TcpListener::from(std_listener.try_clone().unwrap());
+            let second = TcpListener::from(std_listener);
+            let mut first = first.accept();
+            let mut second = second.accept();
+            assert!(futures::poll!(&mut first).is_pending());
+            assert!(futures::poll!(&mut second).is_pending());
+
+            let _client = TcpStream::connect(addr).unwrap();
+            let (winner, loser) = futures::future::select(first, second).await.factor_first();
+            winner.unwrap();
+
+            // the other one keeps waiting
+            let _client = TcpStream::connect(addr).unwrap();
+            loser.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn pipe_write_larger_than_free_space() {
+        on_epoll(|| async {
+            let (r, w) = pipe().unwrap();
+            let data = vec![7; 1 << 20];
+            // a short write instead of blocking until someone reads
+            let n = w.write(&data).await.unwrap();
+            assert!(n < data.len());
+
+            let mut got = Vec::new();
+            let (written, read) = futures::join!(
+                async {
+                    w.write_all(&data[n..]).await?;
+                    w.close().await
+                },
+                r.read_to_end(&mut got),
+            );
+            written.unwrap();
+            read.unwrap();
+            assert_eq!(got, data);
+        });
+    }
+}
@@ -0,0 +1,75 @@
+use std::io;
+
+use io_uring::{squeue, IoUring};
+
+use crate::reactor::FdTarget;
+
+mod capabilities;
+mod epoll;
+mod uring;
+
+pub use capabilities::Capabilities;
+pub(crate) use epoll::EpollDriver;
+pub(crate) use uring::UringDriver;
+
+/// The backend the reactor submits operations to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DriverKind {
+    IoUring,
+    /// Readiness-based emulation for hosts where io_uring is missing or blocked.
+    ///
+    /// Only plain reads, writes, accepts, closes, fsyncs and polls are emulated, operations
+    /// needing io_uring fail with `ErrorKind::Unsupported`.
+    Epoll,
+}
+
+/// An operation the reactor hands to its driver.
+pub(crate) enum Op {
+    /// `offset` is `u64::MAX` for the current file position.
+    Read { fd: FdTarget, buf: *mut u8, len: u32, offset: u64 },
+    Write { fd: FdTarget, buf: *const u8, len: u32, offset: u64 },
+    Readv { fd: FdTarget, iovecs: *const libc::iovec, len: u32 },
+    Writev { fd: FdTarget, iovecs: *const libc::iovec, len: u32 },
+    Fsync { fd: FdTarget },
+    Close { fd: FdTarget },
+    Accept { fd: FdTarget, addr: *mut libc::sockaddr, addr_len: *mut libc::socklen_t },
+    /// `mask` holds `POLL*` events, with `multi` completions may keep coming with `IORING_CQE_F_MORE`.
+    Poll { fd: FdTarget, mask: u32, multi: bool },
+    /// Anything only io_uring can do, e.g. fixed buffers, direct descriptors or multishot receives.
+    Uring(squeue::Entry),
+}
+
+/// Submission and completion of operations, the reactor keeps the token and waker bookkeeping.
+pub(crate) trait Driver {
+    fn kind(&self) -> DriverKind;
+
+    fn capabilities(&self) -> Capabilities;
+
+    /// Queue `op`, its completion is reported under `token`.
+    fn push(&mut self, token: u64, op: Op);
+
+    /// Queue `ops` as one chain, each one starting once the previous one completed.
+    fn push_link(&mut self, ops: Vec<(u64, Op)>, hard: bool) -> io::Result<()>;
+
+    /// Cancel the operation `token`, it completes with `ECANCELED` if still in flight.
+    fn cancel(&mut self, token: u64);
+
+    /// Hand the queued operations over, so they grab their fds right away.
+    fn submit(&mut self);
+
+    /// Collect the available completions as `(token, result, flags)`, without blocking.
+    fn wait(&mut self, completions: &mut Vec<(u64, i32, u32)>);
+
+    /// The ring, for the registration APIs only io_uring has.
+    fn uring(&mut self) -> Option<&mut IoUring> {
+        None
+    }
+}
+
+/// Build the driver of `kind`.
+pub(crate) fn new(kind: DriverKind) -> io::Result<Box<dyn Driver>> {
+    Ok(match kind {
+        DriverKind::IoUring => Box::new(UringDriver::new(128)?),
+        DriverKind::Epoll => Box::new(EpollDriver::new()?),
+    })
+}
@@ -0,0 +1,114 @@
+use std::io;
+
+use io_uring::{opcode, squeue, IoUring};
+
+use crate::reactor::{with_target, IGNORED_TOKEN};
+
+use super::{Capabilities, Driver, DriverKind, Op};
+
+pub(crate) struct UringDriver {
+    uring: IoUring,
+}
+
+impl UringDriver {
+    pub(crate) fn new(entries: u32) -> io::Result<Self> {
+        Ok(Self {
+            uring: IoUring::new(entries)?,
+        })
+    }
+
+    fn push_sqe(&mut self, sqe: &squeue::Entry) {
+        unsafe {
+            if self.uring.submission().push(sqe).is_err() {
+                // submission queue is full, flush it to the kernel and retry
+                let _ = self.uring.submit();
+                self.uring.submission().push(sqe).unwrap();
+            }
+        }
+    }
+
+    fn push_sqes(&mut self, sqes: &[squeue::Entry]) {
+        unsafe {
+            if self.uring.submission().push_multiple(sqes).is_err() {
+                let _ = self.uring.submit();
+                self.uring.submission().push_multiple(sqes).unwrap();
+            }
+        }
+    }
+}
+
+impl Op {
+    fn into_sqe(self) -> squeue::Entry {
+        match self {
+            Op::Read { fd, buf, len, offset } => with_target!(fd, |fd| opcode::Read::new(fd, buf, len).offset(offset).build()),
+            Op::Write { fd, buf, len, offset } => with_target!(fd, |fd| opcode::Write::new(fd, buf, len).offset(offset).build()),
+            // at the current file position, like readv(2) / writev(2)
+            Op::Readv { fd, iovecs, len } => with_target!(fd, |fd| opcode::Readv::new(fd, iovecs, len).offset(u64::MAX).build()),
+            Op::Writev { fd, iovecs, len } => with_target!(fd, |fd| opcode::Writev::new(fd, iovecs, len).offset(u64::MAX).build()),
+            Op::Fsync { fd } => with_target!(fd, |fd| opcode::Fsync::new(fd).build()),
+            Op::Close { fd } => with_target!(fd, |fd| opcode::Close::new(fd).build()),
+            Op::Accept { fd, addr, addr_len } => {
+                with_target!(fd, |fd| opcode::Accept::new(fd, addr, addr_len).flags(libc::O_CLOEXEC).build())
+            }
+            Op::Poll { fd, mask, multi } => with_target!(fd, |fd| opcode::PollAdd::new(fd, mask).multi(multi).build()),
+            Op::Uring(sqe) => sqe,
+        }
+    }
+}
+
+impl Driver for UringDriver {
+    fn kind(&self) -> DriverKind {
+        DriverKind::IoUring
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::probe(&self.uring)
+    }
+
+    fn push(&mut self, token: u64, op: Op) {
+        let sqe = op.into_sqe().user_data(token);
+        self.push_sqe(&sqe);
+    }
+
+    fn push_link(&mut self, ops: Vec<(u64, Op)>, hard: bool) -> io::Result<()> {
+        if ops.len() > self.uring.submission().capacity() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "link is longer than the submission queue"));
+        }
+
+        let link = if hard { squeue::Flags::IO_HARDLINK } else { squeue::Flags::IO_LINK };
+        let last = ops.len().saturating_sub(1);
+        let sqes: Vec<_> = ops
+            .into_iter()
+            .enumerate()
+            .map(|(i, (token, op))| {
+                let sqe = op.into_sqe().user_data(token);
+                if i < last {
+                    sqe.flags(link)
+                } else {
+                    sqe
+                }
+            })
+            .collect();
+        self.push_sqes(&sqes);
+
+        Ok(())
+    }
+
+    fn cancel(&mut self, token: u64) {
+        let sqe = opcode::AsyncCancel::new(token).build().user_data(IGNORED_TOKEN);
+        self.push_sqe(&sqe);
+    }
+
+    fn submit(&mut self) {
+        let _ = self.uring.submit();
+    }
+
+    fn wait(&mut self, completions: &mut Vec<(u64, i32, u32)>) {
+        let _ = self.uring.submit();
+        completions.extend(self.uring.completion().map(|cqe| (cqe.user_data(), cqe.result(), cqe.flags())));
+    }
+
+    fn uring(&mut self) -> Option<&mut IoUring> {
+        Some(&mut self.uring)
+    }
+}
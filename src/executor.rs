@@ -11,6 +11,8 @@ use futures::{future::LocalBoxFuture, Future, FutureExt};
 
 use crate::reactor::Reactor;
 
+pub use crate::driver::{Capabilities, DriverKind};
+
 scoped_tls::scoped_thread_local!(pub(crate) static EX: Executor);
 
 pub struct Executor {
@@ -29,15 +31,40 @@ impl Default for Executor {
 
 
 impl Executor {
+    /// Create an executor on io_uring, falling back to epoll if the kernel doesn't allow it.
     pub fn new() -> Self {
+        Self::with_reactor(Reactor::default())
+    }
+
+    /// Create an executor on the given driver, failing if it isn't available.
+    pub fn with_driver(kind: DriverKind) -> std::io::Result<Self> {
+        Ok(Self::with_reactor(Reactor::with_driver_kind(kind)?))
+    }
+
+    /// Configure an executor before creating it.
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    fn with_reactor(reactor: Reactor) -> Self {
         Self {
             local_queue: TaskQueue::default(),
-            reactor: Rc::new(RefCell::new(Reactor::default())),
+            reactor: Rc::new(RefCell::new(reactor)),
 
             _marker: PhantomData,
         }
     }
 
+    /// The driver the executor ended up on.
+    pub fn driver_kind(&self) -> DriverKind {
+        self.reactor.borrow().driver_kind()
+    }
+
+    /// What the driver and the kernel support, worth logging at startup.
+    pub fn capabilities(&self) -> Capabilities {
+        self.reactor.borrow().capabilities().clone()
+    }
+
     pub fn spawn(fut: impl Future<Output = ()> + 'static) {
         let t = Rc::new(Task {
             future: RefCell::new(fut.boxed_local()),
@@ -82,6 +109,43 @@ impl Executor {
     }
 }
 
+/// Options of an [`Executor`], from [`Executor::builder`].
+#[derive(Default)]
+pub struct Builder {
+    driver: Option<DriverKind>,
+    fixed_files: Option<u32>,
+}
+
+impl Builder {
+    /// Run on the given driver, failing to build if it isn't available. Defaults to io_uring
+    /// with an epoll fallback.
+    pub fn driver(mut self, kind: DriverKind) -> Self {
+        self.driver = Some(kind);
+        self
+    }
+
+    /// Size of the ring's fixed file table, which holds registered fds and direct descriptors.
+    ///
+    /// The table is registered on first use and can't grow afterwards, registering or opening
+    /// more files than it holds fails. The kernel refuses sizes above `RLIMIT_NOFILE`, which
+    /// the default of 64Ki slots is capped to.
+    pub fn fixed_files(mut self, len: u32) -> Self {
+        self.fixed_files = Some(len);
+        self
+    }
+
+    pub fn build(self) -> std::io::Result<Executor> {
+        let mut reactor = match self.driver {
+            Some(kind) => Reactor::with_driver_kind(kind)?,
+            None => Reactor::new(),
+        };
+        if let Some(len) = self.fixed_files {
+            reactor.set_fixed_files(len);
+        }
+        Ok(Executor::with_reactor(reactor))
+    }
+}
+
 pub struct TaskQueue {
     queue: RefCell<VecDeque<Rc<Task>>>,
 }
@@ -169,4 +233,25 @@ unsafe fn increase_refcount(data: *const ()) {
     let rc = mem::ManuallyDrop::new(Rc::<Task>::from_raw(data as *const Task));
     // Now increase refcount, but don't drop new refcount either
     let _rc_clone: mem::ManuallyDrop<_> = rc.clone();
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::File;
+
+    #[test]
+    fn fixed_file_table_size() {
+        let ex = Executor::builder().driver(DriverKind::IoUring).fixed_files(2).build().unwrap();
+        ex.block_on(|| async {
+            let open = || File::from(std::fs::File::open("/dev/null").unwrap());
+            let (a, b, c) = (open(), open(), open());
+            a.register_fixed().unwrap();
+            b.register_fixed().unwrap();
+            assert!(c.register_fixed().is_err());
+
+            // closing frees the slot
+            a.close().await.unwrap();
+            c.register_fixed().unwrap();
+        });
+    }
+}
@@ -1,22 +1,24 @@
 use std::{
-    cell::RefCell,
+    ffi::CString,
     future::Future,
     io,
     os::fd::{AsRawFd, IntoRawFd, RawFd},
-    rc::{Rc, Weak},
+    task::{Context, Poll},
 };
 
 use crate::{
-    io::{AsyncReader, AsyncWriter},
-    reactor::{get_reactor, Reactor},
+    buf::FixedBuf,
+    io::{
+        read_owned, write_all_vectored, write_owned, AsyncFixedReader, AsyncFixedWriter, AsyncRead, AsyncReader, AsyncWrite, AsyncWriter, LinkFd,
+        ReadVectored, SharedFd, WriteVectored,
+    },
+    reactor::ReactorRef,
 };
 
 const AT_FDWCD: isize = -100;
 
 pub struct File {
-    fd: RawFd,
-
-    reactor: Weak<RefCell<Reactor>>,
+    fd: SharedFd,
 }
 
 impl File {
@@ -25,47 +27,180 @@ impl File {
         // println!("path: {:?}", path.as_c_str().to_bytes());
         unsafe {
             let fd = libc::openat(AT_FDWCD as i32, path.as_ptr() as *const _, libc::O_RDONLY);
-            let reactor = get_reactor();
             Self {
-                fd,
-                reactor: Rc::downgrade(&reactor),
+                fd: SharedFd::new(fd),
             }
         }
     }
 
+    /// Open a file read-only as a direct descriptor, straight into the ring's fixed file table.
+    ///
+    /// The file has no regular fd, so its `as_raw_fd` returns -1.
+    pub async fn open_direct(path: &str) -> io::Result<Self> {
+        let path = CString::new(path).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        DirectOpener::new(path, libc::O_RDONLY).await
+    }
+
     pub fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = io::Result<usize>> + 'a {
-        AsyncReader::new(self.fd, buf)
+        AsyncReader::with_target(self.fd.target(), buf)
     }
 
     pub fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = io::Result<usize>> + 'a {
-        AsyncWriter::new(self.fd, buf)
+        AsyncWriter::with_target(self.fd.target(), buf)
+    }
+
+    /// Read into several buffers with a single `IORING_OP_READV`, filling them in order.
+    pub fn read_vectored<B: AsMut<[u8]> + 'static>(&self, bufs: Vec<B>) -> ReadVectored<B> {
+        ReadVectored::new(self.fd.target(), bufs)
+    }
+
+    /// Write several buffers with a single `IORING_OP_WRITEV`. The count may be short.
+    pub fn write_vectored<B: AsRef<[u8]> + 'static>(&self, bufs: Vec<B>) -> WriteVectored<B> {
+        WriteVectored::new(self.fd.target(), bufs)
+    }
+
+    /// Write the whole of `bufs`, resubmitting after short writes.
+    pub async fn write_all_vectored<B: AsRef<[u8]> + 'static>(&self, bufs: Vec<B>) -> (io::Result<()>, Vec<B>) {
+        write_all_vectored(self.fd.target(), bufs).await
+    }
+
+    /// Read into a registered buffer at `pos`, filling it up to its capacity. Resolves to the
+    /// result and the buffer.
+    pub fn read_fixed_at(&self, buf: FixedBuf, pos: u64) -> AsyncFixedReader {
+        AsyncFixedReader::with_target(self.fd.target(), buf, pos)
+    }
+
+    /// Write the content of a registered buffer at `pos`, resolving to the result and the buffer.
+    pub fn write_fixed_at(&self, buf: FixedBuf, pos: u64) -> AsyncFixedWriter {
+        AsyncFixedWriter::with_target(self.fd.target(), buf, pos)
+    }
+
+    /// Register the file into the ring's fixed file table, later operations
+    /// are issued against the fixed slot instead of the fd.
+    pub fn register_fixed(&self) -> io::Result<()> {
+        self.fd.register_fixed()
+    }
+
+    pub fn is_fixed(&self) -> bool {
+        self.fd.is_fixed()
+    }
+
+    /// Close the file, reporting any error returned by the kernel.
+    ///
+    /// Dropping a `File` closes it as well, but the result is discarded.
+    pub async fn close(self) -> io::Result<()> {
+        self.fd.close().await
     }
 }
 
 impl From<std::fs::File> for File {
     fn from(file: std::fs::File) -> Self {
         let fd = file.into_raw_fd();
-        let reactor = get_reactor();
         Self {
-            fd,
-            reactor: Rc::downgrade(&reactor),
+            fd: SharedFd::new(fd),
         }
     }
 }
 
-impl Drop for File {
-    fn drop(&mut self) {
-        unsafe {
-            let reactor = self.reactor.upgrade().unwrap();
-            reactor.borrow_mut().unregister_fd(self.fd);
+impl AsyncRead for File {
+    fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = io::Result<usize>> + 'a {
+        File::read(self, buf)
+    }
 
-            libc::close(self.fd);
-        }
+    fn read_owned<B: AsMut<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (io::Result<usize>, B)> + '_ {
+        read_owned(self.fd.target(), buf)
+    }
+}
+
+impl AsyncWrite for File {
+    fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = io::Result<usize>> + 'a {
+        File::write(self, buf)
+    }
+
+    fn write_owned<B: AsRef<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (io::Result<usize>, B)> + '_ {
+        write_owned(self.fd.target(), buf)
+    }
+}
+
+impl From<&File> for LinkFd {
+    fn from(file: &File) -> Self {
+        LinkFd::from_target(file.fd.target())
     }
 }
 
 impl AsRawFd for File {
     fn as_raw_fd(&self) -> RawFd {
-        self.fd
+        self.fd.as_raw_fd()
+    }
+}
+
+struct DirectOpener {
+    path: CString,
+    flags: i32,
+    token: Option<u64>,
+    // fixed file table slot the file is opened into
+    slot: Option<u32>,
+    reactor: ReactorRef,
+}
+
+impl DirectOpener {
+    fn new(path: CString, flags: i32) -> Self {
+        Self {
+            path,
+            flags,
+            token: None,
+            slot: None,
+            reactor: ReactorRef::default(),
+        }
+    }
+}
+
+impl Future for DirectOpener {
+    type Output = io::Result<File>;
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let reactor = self.reactor.get()?;
+        let mut reactor = reactor.borrow_mut();
+
+        if let Some(token) = self.token {
+            match reactor.take_token_result(token) {
+                Some(result) => {
+                    self.token = None;
+                    let slot = self.slot.take().unwrap();
+                    if result < 0 {
+                        reactor.release_file_slot(slot);
+                        Poll::Ready(Err(io::Error::from_raw_os_error(-result)))
+                    } else {
+                        Poll::Ready(Ok(File {
+                            fd: SharedFd::new_direct(slot, self.reactor.clone()),
+                        }))
+                    }
+                }
+                None => Poll::Pending,
+            }
+        } else {
+            let slot = match reactor.reserve_file_slot() {
+                Ok(slot) => slot,
+                Err(e) => return Poll::Ready(Err(e)),
+            };
+            let token = reactor.openat(cx, self.path.as_ptr(), self.flags, 0, Some(slot));
+            self.slot = Some(slot);
+            self.token = Some(token);
+
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for DirectOpener {
+    fn drop(&mut self) {
+        if let (Some(token), Some(slot)) = (self.token, self.slot) {
+            if let Some(reactor) = self.reactor.bound() {
+                let mut reactor = reactor.borrow_mut();
+                reactor.release_file_slot_after(token, slot);
+                // the path may not have been read by the kernel yet
+                reactor.cancel_and_keep(token, Box::new(std::mem::take(&mut self.path)));
+            }
+        }
     }
 }
@@ -0,0 +1,283 @@
+use std::{
+    cell::Cell,
+    future::Future,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    os::fd::{AsRawFd, RawFd},
+    task::{Context, Poll},
+};
+
+use io_uring::cqueue;
+
+use super::{AsyncRead, AsyncWrite};
+use crate::reactor::ReactorRef;
+
+/// Readiness notifications for an fd the runtime doesn't do the I/O on, through `IORING_OP_POLL_ADD`.
+///
+/// Meant for inotify, netlink, timerfds or fds owned by C libraries. The fd should be
+/// non-blocking: wait with [`readable`](Self::readable) / [`writable`](Self::writable), do the
+/// I/O until it fails with `WouldBlock`, then clear the readiness on the guard.
+///
+/// Through [`AsyncRead`] / [`AsyncWrite`] it waits for readiness then does read(2) / write(2)
+/// itself, which only works on a non-blocking fd.
+///
+/// A multishot poll stays armed for each direction while the `AsyncFd` lives, falling back to
+/// one-shot polls on kernels without it. The fd is not closed on drop, `T` is just dropped.
+pub struct AsyncFd<T: AsRawFd> {
+    inner: Option<T>,
+    read: Direction,
+    write: Direction,
+    reactor: ReactorRef,
+}
+
+#[derive(Default)]
+struct Direction {
+    // the armed poll
+    token: Cell<Option<u64>>,
+    ready: Cell<bool>,
+}
+
+#[derive(Clone, Copy)]
+enum Interest {
+    Readable,
+    Writable,
+}
+
+impl Interest {
+    fn mask(self) -> u32 {
+        match self {
+            Interest::Readable => (libc::POLLIN | libc::POLLRDHUP) as u32,
+            Interest::Writable => libc::POLLOUT as u32,
+        }
+    }
+}
+
+impl<T: AsRawFd> AsyncFd<T> {
+    pub fn new(inner: T) -> IoResult<Self> {
+        if inner.as_raw_fd() < 0 {
+            return Err(IoError::new(ErrorKind::InvalidInput, "invalid fd"));
+        }
+
+        Ok(Self {
+            inner: Some(inner),
+            read: Direction::default(),
+            write: Direction::default(),
+            reactor: ReactorRef::default(),
+        })
+    }
+
+    pub fn get_ref(&self) -> &T {
+        self.inner.as_ref().unwrap()
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.as_mut().unwrap()
+    }
+
+    /// Stop polling and give back the wrapped value.
+    pub fn into_inner(mut self) -> T {
+        self.disarm();
+        self.inner.take().unwrap()
+    }
+
+    /// Wait until the fd is readable, or has hung up or failed.
+    pub fn readable(&self) -> Readiness<'_, T> {
+        Readiness {
+            fd: self,
+            interest: Interest::Readable,
+        }
+    }
+
+    /// Wait until the fd is writable, or has hung up or failed.
+    pub fn writable(&self) -> Readiness<'_, T> {
+        Readiness {
+            fd: self,
+            interest: Interest::Writable,
+        }
+    }
+
+    fn direction(&self, interest: Interest) -> &Direction {
+        match interest {
+            Interest::Readable => &self.read,
+            Interest::Writable => &self.write,
+        }
+    }
+
+    fn disarm(&mut self) {
+        let Some(reactor) = self.reactor.bound() else {
+            return;
+        };
+        let mut reactor = reactor.borrow_mut();
+        for direction in [&self.read, &self.write] {
+            if let Some(token) = direction.token.take() {
+                // drops the queued completions as well
+                reactor.orphan_token(token, |_, _| {});
+            }
+        }
+    }
+}
+
+impl<T: AsRawFd> AsRawFd for AsyncFd<T> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.get_ref().as_raw_fd()
+    }
+}
+
+impl<T: AsRawFd> Drop for AsyncFd<T> {
+    fn drop(&mut self) {
+        self.disarm();
+    }
+}
+
+impl<T: AsRawFd> AsyncFd<T> {
+    async fn read_when_ready(&self, buf: &mut [u8]) -> IoResult<usize> {
+        loop {
+            let mut guard = self.readable().await?;
+            let read = guard.try_io(|inner| {
+                let n = unsafe { libc::read(inner.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+                if n < 0 {
+                    return Err(IoError::last_os_error());
+                }
+                Ok(n as usize)
+            });
+            if let Some(result) = read {
+                return result;
+            }
+        }
+    }
+
+    async fn write_when_ready(&self, buf: &[u8]) -> IoResult<usize> {
+        loop {
+            let mut guard = self.writable().await?;
+            let written = guard.try_io(|inner| {
+                let n = unsafe { libc::write(inner.as_raw_fd(), buf.as_ptr().cast(), buf.len()) };
+                if n < 0 {
+                    return Err(IoError::last_os_error());
+                }
+                Ok(n as usize)
+            });
+            if let Some(result) = written {
+                return result;
+            }
+        }
+    }
+}
+
+impl<T: AsRawFd> AsyncRead for AsyncFd<T> {
+    fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        self.read_when_ready(buf)
+    }
+}
+
+impl<T: AsRawFd> AsyncWrite for AsyncFd<T> {
+    fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        self.write_when_ready(buf)
+    }
+}
+
+/// Future returned by [`AsyncFd::readable`] and [`AsyncFd::writable`].
+pub struct Readiness<'a, T: AsRawFd> {
+    fd: &'a AsyncFd<T>,
+    interest: Interest,
+}
+
+impl<'a, T: AsRawFd> Future for Readiness<'a, T> {
+    type Output = IoResult<ReadyGuard<'a, T>>;
+
+    fn poll(self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let fd = self.fd;
+        let interest = self.interest;
+        let direction = fd.direction(interest);
+        let reactor = fd.reactor.get()?;
+        let mut reactor = reactor.borrow_mut();
+
+        loop {
+            if direction.ready.get() {
+                return Poll::Ready(Ok(ReadyGuard { fd, interest }));
+            }
+
+            let Some(token) = direction.token.get() else {
+                let token = reactor.poll_add(fd.as_raw_fd(), cx, interest.mask(), true);
+                direction.token.set(Some(token));
+                return Poll::Pending;
+            };
+
+            while let Some((result, flags)) = reactor.poll_multishot(token, cx.waker()) {
+                if result >= 0 {
+                    direction.ready.set(true);
+                } else if result != -libc::ECANCELED {
+                    if !cqueue::more(flags) {
+                        direction.token.set(None);
+                    }
+                    return Poll::Ready(Err(IoError::from_raw_os_error(-result)));
+                }
+
+                if !cqueue::more(flags) {
+                    direction.token.set(None);
+                    break;
+                }
+            }
+
+            if direction.token.get().is_some() && !direction.ready.get() {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+/// Proof the fd was ready, until [`clear_ready`](Self::clear_ready) says otherwise.
+pub struct ReadyGuard<'a, T: AsRawFd> {
+    fd: &'a AsyncFd<T>,
+    interest: Interest,
+}
+
+impl<'a, T: AsRawFd> ReadyGuard<'a, T> {
+    pub fn get_ref(&self) -> &'a T {
+        self.fd.get_ref()
+    }
+
+    /// The fd has been drained, wait for the next notification.
+    pub fn clear_ready(&mut self) {
+        self.fd.direction(self.interest).ready.set(false);
+    }
+
+    /// Run `f` on the wrapped value, clearing the readiness if it fails with `WouldBlock`.
+    ///
+    /// Returns `None` in that case, the caller should wait for readiness again.
+    pub fn try_io<R>(&mut self, f: impl FnOnce(&T) -> IoResult<R>) -> Option<IoResult<R>> {
+        match f(self.get_ref()) {
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                self.clear_ready();
+                None
+            }
+            result => Some(result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixStream;
+
+    use super::*;
+    use crate::{
+        executor::Executor,
+        io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    #[test]
+    fn read_and_write_through_the_traits() {
+        Executor::new().block_on(|| async {
+            let (a, b) = UnixStream::pair().unwrap();
+            a.set_nonblocking(true).unwrap();
+            b.set_nonblocking(true).unwrap();
+            let (a, b) = (AsyncFd::new(a).unwrap(), AsyncFd::new(b).unwrap());
+
+            // b waits for a's write
+            let mut buf = [0; 5];
+            let (read, written) = futures::join!(b.read_exact(&mut buf), a.write_all(b"hello"));
+            read.unwrap();
+            written.unwrap();
+            assert_eq!(&buf, b"hello");
+        });
+    }
+}
@@ -0,0 +1,104 @@
+use std::{future::Future, io::Result as IoResult, rc::Rc};
+
+/// Something bytes can be read from: files, sockets, pipes, stdin.
+///
+/// The buffer is handed to the kernel when the future is first polled, so it stays
+/// borrowed until the future resolves. Reads happen at the current file position.
+pub trait AsyncRead {
+    /// Read into `buf`, resolving to the number of bytes read, 0 at end of file.
+    fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a;
+
+    /// Read into an owned buffer, resolving to the result and the buffer.
+    ///
+    /// Unlike `read` the future may be dropped at any time: the read is cancelled and the
+    /// buffer stays with the runtime until the kernel is done with it.
+    fn read_owned<B: AsMut<[u8]> + 'static>(&self, mut buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        // fine for types the kernel never writes into, fds override it
+        async move {
+            let result = self.read(buf.as_mut()).await;
+            (result, buf)
+        }
+    }
+}
+
+/// Something bytes can be written to.
+pub trait AsyncWrite {
+    /// Write from `buf`, resolving to the number of bytes written, which may be short.
+    fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a;
+
+    /// Write from an owned buffer, resolving to the result and the buffer. The future may
+    /// be dropped at any time, like the one of [`AsyncRead::read_owned`].
+    fn write_owned<B: AsRef<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        async move {
+            let result = self.write(buf.as_ref()).await;
+            (result, buf)
+        }
+    }
+
+    /// Write out what is buffered in userspace, nothing to do for plain fds.
+    fn flush(&self) -> impl Future<Output = IoResult<()>> + '_ {
+        std::future::ready(Ok(()))
+    }
+
+    /// Flush, then tell the other end nothing more will be written, e.g. a TCP FIN.
+    /// Only flushes by default.
+    fn shutdown(&self) -> impl Future<Output = IoResult<()>> + '_ {
+        self.flush()
+    }
+}
+
+impl<T: AsyncRead + ?Sized> AsyncRead for &T {
+    fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        (**self).read(buf)
+    }
+
+    fn read_owned<B: AsMut<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        (**self).read_owned(buf)
+    }
+}
+
+impl<T: AsyncRead + ?Sized> AsyncRead for Rc<T> {
+    fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        (**self).read(buf)
+    }
+
+    fn read_owned<B: AsMut<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        (**self).read_owned(buf)
+    }
+}
+
+impl<T: AsyncWrite + ?Sized> AsyncWrite for &T {
+    fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        (**self).write(buf)
+    }
+
+    fn write_owned<B: AsRef<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        (**self).write_owned(buf)
+    }
+
+    fn flush(&self) -> impl Future<Output = IoResult<()>> + '_ {
+        (**self).flush()
+    }
+
+    fn shutdown(&self) -> impl Future<Output = IoResult<()>> + '_ {
+        (**self).shutdown()
+    }
+}
+
+impl<T: AsyncWrite + ?Sized> AsyncWrite for Rc<T> {
+    fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        (**self).write(buf)
+    }
+
+    fn write_owned<B: AsRef<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        (**self).write_owned(buf)
+    }
+
+    fn flush(&self) -> impl Future<Output = IoResult<()>> + '_ {
+        (**self).flush()
+    }
+
+    fn shutdown(&self) -> impl Future<Output = IoResult<()>> + '_ {
+        (**self).shutdown()
+    }
+}
@@ -0,0 +1,368 @@
+use std::{
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    rc::Rc,
+};
+
+use futures::{future::LocalBoxFuture, FutureExt, Stream};
+
+use super::{detach, AsyncRead, AsyncWrite};
+
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// Reads through a buffer it owns, so many small reads cost a single SQE.
+///
+/// The buffer is handed to the read filling it. If a future is dropped while that read is
+/// in flight, the next call picks it up again, and dropping the `BufReader` leaves it to
+/// complete on its own.
+pub struct BufReader<R> {
+    inner: Rc<R>,
+    // `None` while a read fills it
+    buf: Option<Vec<u8>>,
+    fill_op: Option<LocalBoxFuture<'static, (IoResult<usize>, Vec<u8>)>>,
+    capacity: usize,
+    pos: usize,
+    filled: usize,
+}
+
+impl<R: AsyncRead + 'static> BufReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, inner)
+    }
+
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self {
+            inner: Rc::new(inner),
+            buf: Some(vec![0; capacity]),
+            fill_op: None,
+            capacity,
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Give back the reader, dropping what is buffered. Waits for a read left in flight by a
+    /// dropped future first.
+    pub async fn into_inner(mut self) -> R {
+        if let Some(op) = self.fill_op.take() {
+            let _ = op.await;
+        }
+        let inner = self.inner.clone();
+        drop(self);
+        Rc::into_inner(inner).unwrap()
+    }
+
+    /// What has been read but not consumed yet.
+    pub fn buffer(&self) -> &[u8] {
+        match &self.buf {
+            Some(buf) => &buf[self.pos..self.filled],
+            None => &[],
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The buffered bytes, reading more first if there are none. Empty at end of file.
+    pub async fn fill_buf(&mut self) -> IoResult<&[u8]> {
+        if self.pos >= self.filled {
+            if self.fill_op.is_none() {
+                let inner = self.inner.clone();
+                let mut buf = self.buf.take().unwrap();
+                self.fill_op = Some(
+                    async move {
+                        let result = inner.read(&mut buf).await;
+                        (result, buf)
+                    }
+                    .boxed_local(),
+                );
+            }
+
+            let (result, buf) = self.fill_op.as_mut().unwrap().await;
+            self.fill_op = None;
+            self.buf = Some(buf);
+            self.pos = 0;
+            self.filled = *result.as_ref().unwrap_or(&0);
+            result?;
+        }
+        Ok(self.buffer())
+    }
+
+    /// Mark `amt` bytes of [`fill_buf`](Self::fill_buf) as used.
+    pub fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+
+    /// Read into `buf`, from the buffer when it holds something.
+    pub async fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        // nothing buffered or being read and a large read, no point copying it
+        if self.pos >= self.filled && self.fill_op.is_none() && buf.len() >= self.capacity {
+            return self.inner.read(buf).await;
+        }
+
+        let available = self.fill_buf().await?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+
+        Ok(n)
+    }
+
+    /// Append to `buf` up to and including `byte`, or until the end of file.
+    /// Resolves to the number of bytes appended, 0 at end of file.
+    pub async fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> IoResult<usize> {
+        let mut read = 0;
+        loop {
+            let available = match self.fill_buf().await {
+                Ok(available) => available,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            let (done, used) = match available.iter().position(|&b| b == byte) {
+                Some(i) => (true, i + 1),
+                None => (available.is_empty(), available.len()),
+            };
+            buf.extend_from_slice(&available[..used]);
+            self.consume(used);
+            read += used;
+
+            if done {
+                return Ok(read);
+            }
+        }
+    }
+
+    /// Append a line to `buf`, including its `\n`. Resolves to 0 at end of file.
+    pub async fn read_line(&mut self, buf: &mut String) -> IoResult<usize> {
+        let mut bytes = Vec::new();
+        let n = self.read_until(b'\n', &mut bytes).await?;
+        let line = String::from_utf8(bytes)
+            .map_err(|_| IoError::new(ErrorKind::InvalidData, "stream did not contain valid UTF-8"))?;
+        buf.push_str(&line);
+
+        Ok(n)
+    }
+
+    /// The remaining lines, without their `\n` or `\r\n`. Ends at end of file or after an error.
+    pub fn lines(self) -> impl Stream<Item = IoResult<String>> {
+        futures::stream::unfold(Some(self), |reader| async move {
+            let mut reader = reader?;
+            let mut line = String::new();
+            match reader.read_line(&mut line).await {
+                Ok(0) => None,
+                Ok(_) => {
+                    if line.ends_with('\n') {
+                        line.pop();
+                        if line.ends_with('\r') {
+                            line.pop();
+                        }
+                    }
+                    Some((Ok(line), Some(reader)))
+                }
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+}
+
+impl<R> Drop for BufReader<R> {
+    fn drop(&mut self) {
+        if let Some(op) = self.fill_op.take() {
+            detach(op);
+        }
+    }
+}
+
+/// Coalesces small writes into a buffer it owns, written out once full or on [`flush`](Self::flush).
+///
+/// The buffer is handed to the writes emptying it, which are picked up again by the next
+/// call if a future is dropped in the middle. Dropping a `BufWriter` drops what wasn't
+/// flushed, there is no way to write it from `Drop`.
+pub struct BufWriter<W> {
+    inner: Rc<W>,
+    buf: Vec<u8>,
+    // writes out what was buffered, giving back what is left of it
+    flush_op: Option<LocalBoxFuture<'static, (IoResult<()>, Vec<u8>)>>,
+    capacity: usize,
+}
+
+impl<W: AsyncWrite + 'static> BufWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, inner)
+    }
+
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        Self {
+            inner: Rc::new(inner),
+            buf: Vec::with_capacity(capacity),
+            flush_op: None,
+            capacity,
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Give back the writer, dropping what is buffered. Flush first. Waits for a write left
+    /// in flight by a dropped future.
+    pub async fn into_inner(mut self) -> W {
+        if let Some(op) = self.flush_op.take() {
+            let _ = op.await;
+        }
+        let inner = self.inner.clone();
+        drop(self);
+        Rc::into_inner(inner).unwrap()
+    }
+
+    /// What has been written but not flushed yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Buffer `buf`, writing the buffer out first if it doesn't fit.
+    ///
+    /// Writes as large as the buffer go straight to the inner writer.
+    pub async fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        if self.flush_op.is_some() || self.buf.len() + buf.len() > self.capacity {
+            self.flush_buf().await?;
+        }
+        if buf.len() >= self.capacity {
+            return self.inner.write(buf).await;
+        }
+
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    pub async fn write_all(&mut self, mut buf: &[u8]) -> IoResult<()> {
+        while !buf.is_empty() {
+            match self.write(buf).await {
+                Ok(0) => return Err(IoError::from(ErrorKind::WriteZero)),
+                Ok(n) => buf = &buf[n..],
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Write out the buffer, then flush the inner writer.
+    pub async fn flush(&mut self) -> IoResult<()> {
+        self.flush_buf().await?;
+        self.inner.flush().await
+    }
+
+    /// Flush, then shut down the inner writer.
+    pub async fn shutdown(&mut self) -> IoResult<()> {
+        self.flush_buf().await?;
+        self.inner.shutdown().await
+    }
+
+    async fn flush_buf(&mut self) -> IoResult<()> {
+        if self.flush_op.is_none() {
+            if self.buf.is_empty() {
+                return Ok(());
+            }
+            let inner = self.inner.clone();
+            let mut data = std::mem::take(&mut self.buf);
+            self.flush_op = Some(
+                async move {
+                    let mut written = 0;
+                    let result = loop {
+                        if written == data.len() {
+                            break Ok(());
+                        }
+                        match inner.write(&data[written..]).await {
+                            Ok(0) => break Err(IoError::from(ErrorKind::WriteZero)),
+                            Ok(n) => written += n,
+                            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                            Err(e) => break Err(e),
+                        }
+                    };
+                    // keep what is left for the next attempt
+                    data.drain(..written);
+                    (result, data)
+                }
+                .boxed_local(),
+            );
+        }
+
+        let (result, data) = self.flush_op.as_mut().unwrap().await;
+        self.flush_op = None;
+        self.buf = data;
+
+        result
+    }
+}
+
+impl<W> Drop for BufWriter<W> {
+    fn drop(&mut self) {
+        if let Some(op) = self.flush_op.take() {
+            detach(op);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::block_on, FutureExt};
+
+    use super::*;
+    use crate::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn reader_reused_after_a_dropped_read() {
+        block_on(async {
+            let (a, b) = duplex(16);
+            let mut reader = BufReader::with_capacity(8, b);
+
+            let mut line = String::new();
+            assert!(reader.read_line(&mut line).now_or_never().is_none());
+
+            // the next call picks up the read left in flight
+            a.write_all(b"hello\nworld\n").await.unwrap();
+            line.clear();
+            reader.read_line(&mut line).await.unwrap();
+            assert_eq!(line, "hello\n");
+            line.clear();
+            reader.read_line(&mut line).await.unwrap();
+            assert_eq!(line, "world\n");
+        });
+    }
+
+    #[test]
+    fn writer_reused_after_a_dropped_flush() {
+        block_on(async {
+            let (a, b) = duplex(4);
+            let mut writer = BufWriter::with_capacity(16, a);
+            writer.write_all(b"abcdefgh").await.unwrap();
+
+            // only half fits in the duplex
+            assert!(writer.flush().now_or_never().is_none());
+            let mut buf = [0; 4];
+            b.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"abcd");
+
+            // the rest isn't written twice or lost
+            writer.write_all(b"ij").await.unwrap();
+            let read = async {
+                let mut buf = [0; 6];
+                b.read_exact(&mut buf).await.unwrap();
+                buf
+            };
+            let (flushed, buf) = futures::join!(writer.flush(), read);
+            flushed.unwrap();
+            assert_eq!(&buf, b"efghij");
+            assert!(writer.buffer().is_empty());
+        });
+    }
+}
@@ -0,0 +1,305 @@
+//! Adapters to the poll-based `futures::io` traits, and tokio's behind the `tokio` feature.
+//!
+//! Those traits lend a buffer for a single poll only, while the kernel needs one for the
+//! whole operation. The adapter reads into and writes from buffers it owns, copying to and
+//! from the caller's.
+
+use std::{
+    future::Future,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use futures::{future::LocalBoxFuture, FutureExt};
+
+use crate::executor::{Executor, EX};
+
+use super::{AsyncRead, AsyncWrite};
+
+const BUF_SIZE: usize = 8 * 1024;
+
+// an operation in flight, owning its buffer
+type BufOp = LocalBoxFuture<'static, (IoResult<usize>, Vec<u8>)>;
+
+/// Wraps a [`File`](crate::fs::File), a [`TcpSteam`](crate::net::TcpSteam) or any other
+/// [`AsyncRead`] / [`AsyncWrite`] into `futures::io::AsyncRead` / `AsyncWrite`, and tokio's
+/// with the `tokio` feature.
+///
+/// Writes are buffered until the buffer is full or the adapter is flushed, so callers have to
+/// flush as those traits require. An operation still in flight when the adapter is dropped
+/// completes in the background, keeping its buffer and the inner value alive until then.
+pub struct Compat<T> {
+    inner: Rc<T>,
+    read: ReadState,
+    write: WriteState,
+}
+
+struct ReadState {
+    // None while a read owns it
+    buf: Option<Vec<u8>>,
+    pos: usize,
+    filled: usize,
+    op: Option<BufOp>,
+}
+
+struct WriteState {
+    // data waiting to be written, None while a write owns it
+    buf: Option<Vec<u8>>,
+    op: Option<BufOp>,
+    flush: Option<LocalBoxFuture<'static, IoResult<()>>>,
+    shutdown: Option<LocalBoxFuture<'static, IoResult<()>>>,
+}
+
+impl<T: 'static> Compat<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner: Rc::new(inner),
+            read: ReadState {
+                buf: Some(vec![0; BUF_SIZE]),
+                pos: 0,
+                filled: 0,
+                op: None,
+            },
+            write: WriteState {
+                buf: Some(Vec::with_capacity(BUF_SIZE)),
+                op: None,
+                flush: None,
+                shutdown: None,
+            },
+        }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: AsyncRead + 'static> Compat<T> {
+    fn poll_read_buf(&mut self, cx: &mut Context<'_>, out: &mut [u8]) -> Poll<IoResult<usize>> {
+        if out.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        let read = &mut self.read;
+
+        if read.pos == read.filled {
+            if read.op.is_none() {
+                let inner = self.inner.clone();
+                let mut buf = read.buf.take().unwrap();
+                read.op = Some(
+                    async move {
+                        let result = inner.read(&mut buf).await;
+                        (result, buf)
+                    }
+                    .boxed_local(),
+                );
+            }
+
+            let (result, buf) = match read.op.as_mut().unwrap().poll_unpin(cx) {
+                Poll::Ready(done) => done,
+                Poll::Pending => return Poll::Pending,
+            };
+            read.op = None;
+            read.buf = Some(buf);
+            read.pos = 0;
+            // nothing buffered if it failed
+            read.filled = *result.as_ref().unwrap_or(&0);
+            result?;
+        }
+
+        let buf = read.buf.as_ref().unwrap();
+        let n = out.len().min(read.filled - read.pos);
+        out[..n].copy_from_slice(&buf[read.pos..read.pos + n]);
+        read.pos += n;
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<T: AsyncWrite + 'static> Compat<T> {
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>, data: &[u8]) -> Poll<IoResult<usize>> {
+        if data.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+
+        loop {
+            if let Some(buf) = self.write.buf.as_mut() {
+                let room = BUF_SIZE - buf.len();
+                if room > 0 {
+                    let n = room.min(data.len());
+                    buf.extend_from_slice(&data[..n]);
+                    return Poll::Ready(Ok(n));
+                }
+            }
+            // full, make room
+            if self.poll_write_out(cx)?.is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+
+    /// Drive one write of the buffered data, ready once it completed.
+    fn poll_write_out(&mut self, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let write = &mut self.write;
+
+        if write.op.is_none() {
+            let inner = self.inner.clone();
+            let buf = write.buf.take().unwrap();
+            write.op = Some(
+                async move {
+                    let result = inner.write(&buf).await;
+                    (result, buf)
+                }
+                .boxed_local(),
+            );
+        }
+
+        let (result, mut buf) = match write.op.as_mut().unwrap().poll_unpin(cx) {
+            Poll::Ready(done) => done,
+            Poll::Pending => return Poll::Pending,
+        };
+        write.op = None;
+        let result = match result {
+            Ok(0) => Err(IoError::from(ErrorKind::WriteZero)),
+            Ok(n) => {
+                buf.drain(..n);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        };
+        write.buf = Some(buf);
+
+        Poll::Ready(result)
+    }
+
+    fn poll_flush_buf(&mut self, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        while self.write.op.is_some() || !self.write.buf.as_ref().unwrap().is_empty() {
+            if self.poll_write_out(cx)?.is_pending() {
+                return Poll::Pending;
+            }
+        }
+
+        if self.write.flush.is_none() {
+            let inner = self.inner.clone();
+            self.write.flush = Some(async move { inner.flush().await }.boxed_local());
+        }
+        let result = futures::ready!(self.write.flush.as_mut().unwrap().poll_unpin(cx));
+        self.write.flush = None;
+
+        Poll::Ready(result)
+    }
+
+    fn poll_shutdown_inner(&mut self, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        if self.write.shutdown.is_none() {
+            futures::ready!(self.poll_flush_buf(cx))?;
+            let inner = self.inner.clone();
+            self.write.shutdown = Some(async move { inner.shutdown().await }.boxed_local());
+        }
+        let result = futures::ready!(self.write.shutdown.as_mut().unwrap().poll_unpin(cx));
+        self.write.shutdown = None;
+
+        Poll::Ready(result)
+    }
+}
+
+impl<T> Drop for Compat<T> {
+    fn drop(&mut self) {
+        let ops = [self.read.op.take(), self.write.op.take()];
+        for op in ops.into_iter().flatten() {
+            detach(op);
+        }
+    }
+}
+
+/// Let an operation dropped in flight run to completion, as the kernel may still use
+/// the buffer it owns.
+pub(crate) fn detach<F: Future + 'static>(op: F) {
+    if EX.is_set() {
+        Executor::spawn(op.map(drop));
+    } else {
+        // nothing can drive it anymore
+        std::mem::forget(op);
+    }
+}
+
+impl<T: AsyncRead + 'static> futures::io::AsyncRead for Compat<T> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<IoResult<usize>> {
+        self.get_mut().poll_read_buf(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + 'static> futures::io::AsyncWrite for Compat<T> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        self.get_mut().poll_write_buf(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        self.get_mut().poll_flush_buf(cx)
+    }
+
+    // shuts down the inner value, it is closed when the adapter is dropped
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        self.get_mut().poll_shutdown_inner(cx)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: AsyncRead + 'static> tokio::io::AsyncRead for Compat<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        let n = futures::ready!(self.get_mut().poll_read_buf(cx, buf.initialize_unfilled()))?;
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<T: AsyncWrite + 'static> tokio::io::AsyncWrite for Compat<T> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        self.get_mut().poll_write_buf(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        self.get_mut().poll_flush_buf(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        self.get_mut().poll_shutdown_inner(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use futures::{executor::block_on, io::AsyncReadExt};
+
+    use super::*;
+    use crate::io::MockStream;
+
+    #[test]
+    fn read_error_keeps_no_stale_data() {
+        block_on(async {
+            let stream = MockStream::builder()
+                .read(b"hello")
+                .read_error(IoError::from(ErrorKind::ConnectionReset))
+                .read(b"world")
+                .build();
+            let mut compat = Compat::new(stream);
+
+            let mut buf = [0; 16];
+            assert_eq!(compat.read(&mut buf).await.unwrap(), 5);
+            assert_eq!(&buf[..5], b"hello");
+            let err = compat.read(&mut buf).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+
+            let mut rest = Vec::new();
+            compat.read_to_end(&mut rest).await.unwrap();
+            assert_eq!(rest, b"world");
+        });
+    }
+}
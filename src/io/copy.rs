@@ -0,0 +1,165 @@
+use std::{
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    pin::pin,
+};
+
+use futures::future::{select, Either};
+
+use super::{AsyncRead, AsyncWrite};
+
+const BUF_SIZE: usize = 64 * 1024;
+
+/// Copy everything `reader` yields into `writer`, resolving to the number of bytes copied.
+///
+/// One buffer is written while the next is being read, so a read and a write are in flight
+/// at the same time. A failing write ends the copy without waiting for the read. `writer`
+/// is flushed at the end. Between two fds, [`splice`](super::splice) with `usize::MAX` moves
+/// the data without copying it through userspace.
+///
+/// The buffers are owned by the reads and writes, so an abandoned copy cancels the ones in
+/// flight and leaves their buffers to the runtime until the kernel is done with them.
+pub async fn copy<R, W>(reader: &R, writer: &W) -> IoResult<u64>
+where
+    R: AsyncRead + ?Sized,
+    W: AsyncWrite + ?Sized,
+{
+    let mut copied = 0;
+
+    let (n, mut front) = reader.read_owned(vec![0; BUF_SIZE]).await;
+    let mut n = n?;
+    let mut back = vec![0; BUF_SIZE];
+    while n > 0 {
+        let ((read, filled), (written, drained)) = {
+            let read = pin!(reader.read_owned(back));
+            let write = pin!(write_all_owned(writer, front, n));
+            match select(read, write).await {
+                Either::Left((read, write)) => (read, write.await),
+                // the read may never complete, e.g. an idle peer
+                Either::Right(((Err(e), _), _)) => return Err(e),
+                Either::Right((write, read)) => (read.await, write),
+            }
+        };
+        written?;
+        copied += n as u64;
+        n = read?;
+        (front, back) = (filled, drained);
+    }
+    writer.flush().await?;
+
+    Ok(copied)
+}
+
+// `buf[start..end]`, what is left to write
+struct Window {
+    buf: Vec<u8>,
+    start: usize,
+    end: usize,
+}
+
+impl AsRef<[u8]> for Window {
+    fn as_ref(&self) -> &[u8] {
+        &self.buf[self.start..self.end]
+    }
+}
+
+/// Write the first `len` bytes of `buf`, resolving to the result and the buffer.
+async fn write_all_owned<W: AsyncWrite + ?Sized>(writer: &W, buf: Vec<u8>, len: usize) -> (IoResult<()>, Vec<u8>) {
+    let mut window = Window { buf, start: 0, end: len };
+
+    while window.start < window.end {
+        let (result, returned) = writer.write_owned(window).await;
+        window = returned;
+        match result {
+            Ok(0) => return (Err(IoError::from(ErrorKind::WriteZero)), window.buf),
+            Ok(n) => window.start += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return (Err(e), window.buf),
+        }
+    }
+
+    (Ok(()), window.buf)
+}
+
+/// Copy between `a` and `b` in both directions until both reach end of file, resolving to the
+/// bytes copied from `a` to `b` and from `b` to `a`.
+///
+/// When one side is done writing its peer is shut down for writing, so a half-closed TCP
+/// connection stays half-closed through the proxy. The first error in either direction ends
+/// both.
+pub async fn copy_bidirectional<A, B>(a: &A, b: &B) -> IoResult<(u64, u64)>
+where
+    A: AsyncRead + AsyncWrite + ?Sized,
+    B: AsyncRead + AsyncWrite + ?Sized,
+{
+    futures::try_join!(copy_one_way(a, b), copy_one_way(b, a))
+}
+
+async fn copy_one_way<R, W>(reader: &R, writer: &W) -> IoResult<u64>
+where
+    R: AsyncRead + ?Sized,
+    W: AsyncWrite + ?Sized,
+{
+    let copied = copy(reader, writer).await?;
+    writer.shutdown().await?;
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::block_on, FutureExt};
+
+    use super::*;
+    use crate::{
+        executor::Executor,
+        io::{duplex, pipe, AsyncReadExt, AsyncWriteExt},
+    };
+
+    #[test]
+    fn write_error_ends_copy() {
+        block_on(async {
+            let (src, src_peer) = duplex(64);
+            let (dst, dst_peer) = duplex(64);
+            src_peer.write_all(b"data").await.unwrap();
+            drop(dst_peer);
+
+            // the next read never completes, the write error is returned anyway
+            let copied = copy(&src, &dst).now_or_never().expect("copy waited for the read");
+            assert_eq!(copied.unwrap_err().kind(), ErrorKind::BrokenPipe);
+        });
+    }
+
+    #[test]
+    fn dropped_mid_copy() {
+        block_on(async {
+            let (src, src_peer) = duplex(64);
+            let (dst, dst_peer) = duplex(64);
+            src_peer.write_all(b"abc").await.unwrap();
+
+            assert!(copy(&src, &dst).now_or_never().is_none());
+            let mut buf = [0; 3];
+            dst_peer.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"abc");
+
+            // the abandoned read took nothing
+            src_peer.write_all(b"more").await.unwrap();
+            let mut buf = [0; 4];
+            src.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"more");
+        });
+    }
+
+    #[test]
+    fn dropped_mid_read_on_a_pipe() {
+        Executor::new().block_on(|| async {
+            let (src, src_peer) = pipe().unwrap();
+            let (_dst_peer, dst) = pipe().unwrap();
+
+            // the read in flight is cancelled rather than left to take the next bytes
+            assert!(copy(&src, &dst).now_or_never().is_none());
+            src_peer.write_all(b"later").await.unwrap();
+            let mut buf = [0; 5];
+            src.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"later");
+        });
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    future::{poll_fn, Future},
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    rc::Rc,
+    task::{Poll, Waker},
+};
+
+use super::{AsyncRead, AsyncWrite};
+
+/// A connected pair of in-memory streams, what one writes the other reads.
+///
+/// Each direction buffers up to `capacity` bytes, writes wait for room beyond that.
+/// Shutting down or dropping one end gives the other end EOF on its reads, writing to a
+/// dropped end fails with `BrokenPipe`.
+///
+/// ```
+/// use aruntime::io::{duplex, AsyncReadExt, AsyncWriteExt};
+///
+/// futures::executor::block_on(async {
+///     let (client, server) = duplex(64);
+///     client.write_all(b"hello").await.unwrap();
+///     drop(client);
+///
+///     let mut received = String::new();
+///     server.read_to_string(&mut received).await.unwrap();
+///     assert_eq!(received, "hello");
+/// });
+/// ```
+///
+/// # Panics
+///
+/// If `capacity` is 0.
+pub fn duplex(capacity: usize) -> (DuplexStream, DuplexStream) {
+    assert!(capacity > 0, "duplex capacity must be at least 1");
+
+    let a_to_b = Rc::new(RefCell::new(Pipe::new(capacity)));
+    let b_to_a = Rc::new(RefCell::new(Pipe::new(capacity)));
+    let a = DuplexStream {
+        read: b_to_a.clone(),
+        write: a_to_b.clone(),
+    };
+    let b = DuplexStream {
+        read: a_to_b,
+        write: b_to_a,
+    };
+    (a, b)
+}
+
+/// One end of a [`duplex`] pair.
+pub struct DuplexStream {
+    read: Rc<RefCell<Pipe>>,
+    write: Rc<RefCell<Pipe>>,
+}
+
+struct Pipe {
+    buf: VecDeque<u8>,
+    capacity: usize,
+    // the writing end shut down or went away
+    closed: bool,
+    // the reading end went away
+    reader_gone: bool,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+impl Pipe {
+    fn new(capacity: usize) -> Self {
+        Self {
+            buf: VecDeque::with_capacity(capacity),
+            capacity,
+            closed: false,
+            reader_gone: false,
+            read_waker: None,
+            write_waker: None,
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl AsyncRead for DuplexStream {
+    fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        poll_fn(move |cx| {
+            let mut pipe = self.read.borrow_mut();
+            if buf.is_empty() || pipe.closed && pipe.buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            if pipe.buf.is_empty() {
+                pipe.read_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+
+            // both halves of the ring, `Read for VecDeque` stops at the first
+            let n = buf.len().min(pipe.buf.len());
+            for (dst, byte) in buf.iter_mut().zip(pipe.buf.drain(..n)) {
+                *dst = byte;
+            }
+            if let Some(waker) = pipe.write_waker.take() {
+                waker.wake();
+            }
+            Poll::Ready(Ok(n))
+        })
+    }
+}
+
+impl AsyncWrite for DuplexStream {
+    fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        poll_fn(move |cx| {
+            let mut pipe = self.write.borrow_mut();
+            if pipe.closed || pipe.reader_gone {
+                return Poll::Ready(Err(IoError::from(ErrorKind::BrokenPipe)));
+            }
+            if buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            let room = pipe.capacity - pipe.buf.len();
+            if room == 0 {
+                pipe.write_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+
+            let n = room.min(buf.len());
+            pipe.buf.extend(&buf[..n]);
+            if let Some(waker) = pipe.read_waker.take() {
+                waker.wake();
+            }
+            Poll::Ready(Ok(n))
+        })
+    }
+
+    fn shutdown(&self) -> impl Future<Output = IoResult<()>> + '_ {
+        self.write.borrow_mut().close();
+        std::future::ready(Ok(()))
+    }
+}
+
+impl Drop for DuplexStream {
+    fn drop(&mut self) {
+        self.write.borrow_mut().close();
+
+        let mut read = self.read.borrow_mut();
+        read.reader_gone = true;
+        if let Some(waker) = read.write_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{executor::block_on, FutureExt};
+
+    use super::*;
+    use crate::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn both_directions() {
+        block_on(async {
+            let (a, b) = duplex(64);
+            a.write_all(b"ping").await.unwrap();
+            b.write_all(b"pong").await.unwrap();
+
+            let mut buf = [0; 4];
+            b.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"ping");
+            a.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"pong");
+        });
+    }
+
+    #[test]
+    fn eof_after_shutdown() {
+        block_on(async {
+            let (a, b) = duplex(64);
+            a.write_all(b"last").await.unwrap();
+            a.shutdown().await.unwrap();
+            assert_eq!(a.write(b"x").await.unwrap_err().kind(), ErrorKind::BrokenPipe);
+
+            let mut data = Vec::new();
+            b.read_to_end(&mut data).await.unwrap();
+            assert_eq!(data, b"last");
+
+            // the other direction still works
+            b.write_all(b"reply").await.unwrap();
+            let mut buf = [0; 5];
+            a.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"reply");
+        });
+    }
+
+    #[test]
+    fn drop_gives_eof_and_broken_pipe() {
+        block_on(async {
+            let (a, b) = duplex(64);
+            a.write_all(b"bye").await.unwrap();
+            drop(a);
+
+            let mut data = Vec::new();
+            b.read_to_end(&mut data).await.unwrap();
+            assert_eq!(data, b"bye");
+            assert_eq!(b.write(b"x").await.unwrap_err().kind(), ErrorKind::BrokenPipe);
+        });
+    }
+
+    #[test]
+    fn backpressure() {
+        block_on(async {
+            let (a, b) = duplex(4);
+            assert_eq!(a.write(b"0123456789").await.unwrap(), 4);
+            assert!(a.write(b"456789").now_or_never().is_none());
+
+            let mut buf = [0; 3];
+            assert_eq!(b.read(&mut buf).await.unwrap(), 3);
+            assert_eq!(a.write(b"456789").await.unwrap(), 3);
+
+            let mut buf = [0; 8];
+            assert_eq!(b.read(&mut buf).await.unwrap(), 4);
+            assert_eq!(&buf[..4], b"3456");
+            assert!(b.read(&mut buf).now_or_never().is_none());
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 1")]
+    fn zero_capacity() {
+        duplex(0);
+    }
+}
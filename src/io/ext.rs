@@ -0,0 +1,122 @@
+use std::{
+    future::Future,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+};
+
+use super::{AsyncRead, AsyncWrite};
+
+// smallest read `read_to_end` issues
+const MIN_READ: usize = 8 * 1024;
+
+/// Reads made of several [`AsyncRead::read`] calls, resubmitted until complete.
+pub trait AsyncReadExt: AsyncRead {
+    /// Fill the whole of `buf`, failing with `UnexpectedEof` if the end comes first.
+    ///
+    /// What was read before an error is left in `buf`.
+    fn read_exact<'a>(&'a self, mut buf: &'a mut [u8]) -> impl Future<Output = IoResult<()>> + 'a {
+        async move {
+            while !buf.is_empty() {
+                match self.read(buf).await {
+                    Ok(0) => return Err(IoError::from(ErrorKind::UnexpectedEof)),
+                    Ok(n) => buf = &mut buf[n..],
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// Read until the end, appending to `buf`. Resolves to the number of bytes appended.
+    fn read_to_end<'a>(&'a self, buf: &'a mut Vec<u8>) -> impl Future<Output = IoResult<usize>> + 'a {
+        async move {
+            let start = buf.len();
+            loop {
+                let len = buf.len();
+                // grow along with the vector, so large files take few reads
+                let chunk = MIN_READ.max(buf.capacity() - len);
+                buf.resize(len + chunk, 0);
+
+                match self.read(&mut buf[len..]).await {
+                    Ok(0) => {
+                        buf.truncate(len);
+                        return Ok(len - start);
+                    }
+                    Ok(n) => buf.truncate(len + n),
+                    Err(e) if e.kind() == ErrorKind::Interrupted => buf.truncate(len),
+                    Err(e) => {
+                        buf.truncate(len);
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Read until the end, appending to `buf`, which is left untouched if the data isn't UTF-8.
+    fn read_to_string<'a>(&'a self, buf: &'a mut String) -> impl Future<Output = IoResult<usize>> + 'a {
+        async move {
+            let mut bytes = Vec::new();
+            let n = self.read_to_end(&mut bytes).await?;
+            let s = String::from_utf8(bytes)
+                .map_err(|_| IoError::new(ErrorKind::InvalidData, "stream did not contain valid UTF-8"))?;
+            buf.push_str(&s);
+            Ok(n)
+        }
+    }
+}
+
+impl<R: AsyncRead + ?Sized> AsyncReadExt for R {}
+
+/// Writes made of several [`AsyncWrite::write`] calls, resubmitted until complete.
+pub trait AsyncWriteExt: AsyncWrite {
+    /// Write the whole of `buf`, failing with `WriteZero` if nothing more can be written.
+    fn write_all<'a>(&'a self, mut buf: &'a [u8]) -> impl Future<Output = IoResult<()>> + 'a {
+        async move {
+            while !buf.is_empty() {
+                match self.write(buf).await {
+                    Ok(0) => return Err(IoError::from(ErrorKind::WriteZero)),
+                    Ok(n) => buf = &buf[n..],
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+impl<W: AsyncWrite + ?Sized> AsyncWriteExt for W {}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::io::MockStream;
+
+    #[test]
+    fn read_exact_early_eof() {
+        block_on(async {
+            let stream = MockStream::builder().read(b"ab").read(b"c").build();
+            let mut buf = [0; 5];
+            let err = stream.read_exact(&mut buf).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+            assert_eq!(&buf[..3], b"abc");
+        });
+    }
+
+    #[test]
+    fn read_exact_retries_interrupted_reads() {
+        block_on(async {
+            let stream = MockStream::builder()
+                .read(b"ab")
+                .read_error(ErrorKind::Interrupted.into())
+                .read(b"cd")
+                .build();
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"abcd");
+        });
+    }
+}
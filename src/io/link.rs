@@ -0,0 +1,488 @@
+use std::{
+    ffi::CString,
+    future::Future,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    os::fd::RawFd,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+};
+
+use io_uring::{opcode, squeue, types};
+
+use crate::reactor::{with_target, FdTarget, Reactor, ReactorRef};
+
+use super::completion_result;
+
+/// The file a [`Link`] step operates on.
+#[derive(Clone, Copy, Debug)]
+pub struct LinkFd(LinkTarget);
+
+#[derive(Clone, Copy, Debug)]
+enum LinkTarget {
+    Fd(FdTarget),
+    /// The file opened by step `step` of the link `link`.
+    Opened { link: u64, step: usize },
+}
+
+impl LinkFd {
+    pub(crate) fn from_target(target: FdTarget) -> Self {
+        Self(LinkTarget::Fd(target))
+    }
+}
+
+impl From<RawFd> for LinkFd {
+    fn from(fd: RawFd) -> Self {
+        Self::from_target(FdTarget::Raw(fd))
+    }
+}
+
+// `buf` indexes the buffers owned by the link
+enum Step {
+    Read { fd: LinkFd, buf: usize, offset: u64 },
+    Write { fd: LinkFd, buf: usize, offset: u64 },
+    Fsync { fd: LinkFd },
+    Open { path: CString, flags: i32, mode: libc::mode_t },
+    Close { fd: LinkFd },
+}
+
+/// An ordered chain of operations submitted at once, linked with `IOSQE_IO_LINK`.
+///
+/// Each step only starts once the previous one completed. With the default soft links a
+/// failing or short step cancels the rest of the chain, which then complete with `ECANCELED`.
+///
+/// The link owns the buffers of its steps and gives them back in the order they were added,
+/// so dropping the future while the chain runs leaves them with the reactor until it's over.
+///
+/// ```ignore
+/// let mut link = Link::new();
+/// link.write(&wal, record).fsync(&wal);
+/// let (results, bufs) = link.submit().await;
+/// ```
+pub struct Link {
+    // tells the files opened by this link from those of others
+    id: u64,
+    steps: Vec<Step>,
+    bufs: Vec<Vec<u8>>,
+    hard: bool,
+    error: Option<IoError>,
+}
+
+impl Default for Link {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Link {
+    pub fn new() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            steps: Vec::new(),
+            bufs: Vec::new(),
+            hard: false,
+            error: None,
+        }
+    }
+
+    /// Use `IOSQE_IO_HARDLINK`, so the chain carries on after a failing step.
+    pub fn hard(&mut self) -> &mut Self {
+        self.hard = true;
+        self
+    }
+
+    /// Read at the current file position, which the read advances.
+    pub fn read(&mut self, fd: impl Into<LinkFd>, buf: Vec<u8>) -> &mut Self {
+        self.read_at(fd, buf, u64::MAX)
+    }
+
+    /// Read up to the length of `buf`, not its capacity.
+    pub fn read_at(&mut self, fd: impl Into<LinkFd>, buf: Vec<u8>, pos: u64) -> &mut Self {
+        let buf = self.add_buf(buf);
+        self.steps.push(Step::Read {
+            fd: fd.into(),
+            buf,
+            offset: pos,
+        });
+        self
+    }
+
+    /// Write at the current file position, so successive chains append.
+    pub fn write(&mut self, fd: impl Into<LinkFd>, buf: Vec<u8>) -> &mut Self {
+        self.write_at(fd, buf, u64::MAX)
+    }
+
+    pub fn write_at(&mut self, fd: impl Into<LinkFd>, buf: Vec<u8>, pos: u64) -> &mut Self {
+        let buf = self.add_buf(buf);
+        self.steps.push(Step::Write {
+            fd: fd.into(),
+            buf,
+            offset: pos,
+        });
+        self
+    }
+
+    /// Read into `buf`, then write the whole of `buf` to `to`.
+    ///
+    /// The write doesn't know how much was read, so a short read breaks a soft link,
+    /// and sends stale bytes with a hard one.
+    pub fn relay(&mut self, from: impl Into<LinkFd>, to: impl Into<LinkFd>, buf: Vec<u8>) -> &mut Self {
+        let buf = self.add_buf(buf);
+        self.steps.push(Step::Read {
+            fd: from.into(),
+            buf,
+            offset: u64::MAX,
+        });
+        self.steps.push(Step::Write {
+            fd: to.into(),
+            buf,
+            offset: u64::MAX,
+        });
+        self
+    }
+
+    fn add_buf(&mut self, buf: Vec<u8>) -> usize {
+        self.bufs.push(buf);
+        self.bufs.len() - 1
+    }
+
+    pub fn fsync(&mut self, fd: impl Into<LinkFd>) -> &mut Self {
+        self.steps.push(Step::Fsync { fd: fd.into() });
+        self
+    }
+
+    /// Open `path` read-only as a direct descriptor, usable by the following steps.
+    ///
+    /// Files opened by a link are closed once it completes, if no [`close`](Self::close) step did it before.
+    pub fn open(&mut self, path: &str) -> LinkFd {
+        self.open_with(path, libc::O_RDONLY, 0)
+    }
+
+    /// Open `path` for writing, creating or truncating it, as a direct descriptor.
+    pub fn create(&mut self, path: &str) -> LinkFd {
+        self.open_with(path, libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC, 0o644)
+    }
+
+    fn open_with(&mut self, path: &str, flags: i32, mode: libc::mode_t) -> LinkFd {
+        match CString::new(path) {
+            Ok(path) => self.steps.push(Step::Open { path, flags, mode }),
+            Err(e) => {
+                self.error.get_or_insert(IoError::new(ErrorKind::InvalidInput, e));
+                // keep the step indices in line
+                self.steps.push(Step::Open {
+                    path: CString::default(),
+                    flags,
+                    mode,
+                });
+            }
+        }
+        LinkFd(LinkTarget::Opened {
+            link: self.id,
+            step: self.steps.len() - 1,
+        })
+    }
+
+    /// Close a file opened by this link.
+    pub fn close(&mut self, fd: LinkFd) -> &mut Self {
+        self.steps.push(Step::Close { fd });
+        self
+    }
+
+    /// Submit the chain, resolving to the result of every step in order along with the buffers.
+    pub fn submit(self) -> LinkFuture {
+        LinkFuture::new(self)
+    }
+}
+
+pub struct LinkFuture {
+    link: Link,
+    tokens: Vec<u64>,
+    results: Vec<Option<i32>>,
+    // (step, fixed file table slot) of the files opened by the link
+    slots: Vec<(usize, u32)>,
+    reactor: ReactorRef,
+}
+
+impl LinkFuture {
+    fn new(link: Link) -> Self {
+        Self {
+            link,
+            tokens: Vec::new(),
+            results: Vec::new(),
+            slots: Vec::new(),
+            reactor: ReactorRef::default(),
+        }
+    }
+
+    fn resolve(&self, fd: LinkFd) -> IoResult<FdTarget> {
+        match fd.0 {
+            LinkTarget::Fd(target) => Ok(target),
+            LinkTarget::Opened { link, step } => self
+                .slots
+                .iter()
+                .find(|(opened, _)| link == self.link.id && *opened == step)
+                .map(|(_, slot)| FdTarget::Fixed(*slot))
+                .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, "file wasn't opened by this link")),
+        }
+    }
+
+    fn build(&mut self) -> IoResult<Vec<(FdTarget, squeue::Entry)>> {
+        let mut sqes = Vec::with_capacity(self.link.steps.len());
+        for (i, step) in self.link.steps.iter().enumerate() {
+            let sqe = match step {
+                Step::Read { fd, buf, offset } => {
+                    let fd = self.resolve(*fd)?;
+                    let buf = &mut self.link.bufs[*buf];
+                    let (ptr, len) = (buf.as_mut_ptr(), buf.len() as u32);
+                    (fd, with_target!(fd, |fd| opcode::Read::new(fd, ptr, len).offset(*offset).build()))
+                }
+                Step::Write { fd, buf, offset } => {
+                    let fd = self.resolve(*fd)?;
+                    let buf = &self.link.bufs[*buf];
+                    (fd, with_target!(fd, |fd| opcode::Write::new(fd, buf.as_ptr(), buf.len() as u32).offset(*offset).build()))
+                }
+                Step::Fsync { fd } => {
+                    let fd = self.resolve(*fd)?;
+                    (fd, with_target!(fd, |fd| opcode::Fsync::new(fd).build()))
+                }
+                Step::Open { path, flags, mode } => {
+                    let (_, slot) = self.slots.iter().find(|(step, _)| *step == i).unwrap();
+                    let dest = types::DestinationSlot::try_from_slot_target(*slot).unwrap();
+                    let sqe = opcode::OpenAt::new(types::Fd(libc::AT_FDCWD), path.as_ptr())
+                        .flags(*flags)
+                        .mode(*mode)
+                        .file_index(Some(dest))
+                        .build();
+                    (FdTarget::Raw(libc::AT_FDCWD), sqe)
+                }
+                Step::Close { fd } => match (fd.0, self.resolve(*fd)?) {
+                    (LinkTarget::Opened { .. }, FdTarget::Fixed(slot)) => {
+                        (FdTarget::Fixed(slot), opcode::Close::new(types::Fixed(slot)).build())
+                    }
+                    _ => {
+                        return Err(IoError::new(
+                            ErrorKind::InvalidInput,
+                            "only files opened by the link can be closed by it",
+                        ))
+                    }
+                },
+            };
+            sqes.push(sqe);
+        }
+
+        Ok(sqes)
+    }
+
+    fn release_slots(&mut self, reactor: &mut Reactor) {
+        for (_, slot) in self.slots.drain(..) {
+            reactor.release_file_slot(slot);
+        }
+    }
+}
+
+impl Future for LinkFuture {
+    type Output = (IoResult<Vec<IoResult<usize>>>, Vec<Vec<u8>>);
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let reactor = match self.reactor.get() {
+            Ok(reactor) => reactor,
+            Err(e) => return Poll::Ready((Err(e), std::mem::take(&mut self.link.bufs))),
+        };
+        let mut reactor = reactor.borrow_mut();
+
+        if self.tokens.is_empty() {
+            if let Some(e) = self.link.error.take() {
+                return Poll::Ready((Err(e), std::mem::take(&mut self.link.bufs)));
+            }
+            if self.link.steps.is_empty() {
+                return Poll::Ready((Ok(Vec::new()), std::mem::take(&mut self.link.bufs)));
+            }
+
+            for i in 0..self.link.steps.len() {
+                if let Step::Open { .. } = self.link.steps[i] {
+                    match reactor.reserve_file_slot() {
+                        Ok(slot) => self.slots.push((i, slot)),
+                        Err(e) => {
+                            self.release_slots(&mut reactor);
+                            return Poll::Ready((Err(e), std::mem::take(&mut self.link.bufs)));
+                        }
+                    }
+                }
+            }
+
+            let submitted = self.build().and_then(|sqes| reactor.link(cx, sqes, self.link.hard));
+            match submitted {
+                Ok(tokens) => {
+                    self.results = vec![None; tokens.len()];
+                    self.tokens = tokens;
+                    Poll::Pending
+                }
+                Err(e) => {
+                    self.release_slots(&mut reactor);
+                    Poll::Ready((Err(e), std::mem::take(&mut self.link.bufs)))
+                }
+            }
+        } else {
+            for i in 0..self.tokens.len() {
+                if self.results[i].is_none() {
+                    self.results[i] = reactor.take_token_result(self.tokens[i]);
+                }
+            }
+
+            if self.results.iter().all(Option::is_some) {
+                self.release_slots(&mut reactor);
+                self.tokens.clear();
+                let results = self.results.drain(..).map(|result| completion_result(result.unwrap())).collect();
+                Poll::Ready((Ok(results), std::mem::take(&mut self.link.bufs)))
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for LinkFuture {
+    fn drop(&mut self) {
+        let (Some(&last), Some(reactor)) = (self.tokens.last(), self.reactor.bound()) else {
+            return;
+        };
+        let mut reactor = reactor.borrow_mut();
+
+        let mut bufs: Vec<_> = self.link.bufs.drain(..).map(Some).collect();
+        // the kernel may still use the buffers and paths, each is kept until the last step using it completed
+        for (i, step) in self.link.steps.iter_mut().enumerate().rev() {
+            match step {
+                Step::Open { path, .. } => reactor.cancel_and_keep(self.tokens[i], Box::new(std::mem::take(path))),
+                Step::Read { buf, .. } | Step::Write { buf, .. } => match bufs[*buf].take() {
+                    Some(buf) => reactor.cancel_and_keep(self.tokens[i], Box::new(buf)),
+                    None => reactor.cancel_token(self.tokens[i]),
+                },
+                _ => reactor.cancel_token(self.tokens[i]),
+            }
+        }
+        // the chain completes in order, so the opened files are unused after the last step
+        for (_, slot) in self.slots.drain(..) {
+            reactor.release_file_slot_after(last, slot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::File,
+        io::{Read, Write},
+        os::fd::{AsRawFd, FromRawFd},
+    };
+
+    use futures::FutureExt;
+
+    use super::*;
+    use crate::executor::{DriverKind, Executor};
+
+    fn pipe() -> (File, File) {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) }, 0);
+        unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) }
+    }
+
+    #[test]
+    fn relay_gives_back_buffers() {
+        let ex = Executor::with_driver(DriverKind::IoUring).unwrap();
+        ex.block_on(|| async {
+            let (from_r, mut from_w) = pipe();
+            let (mut to_r, to_w) = pipe();
+            from_w.write_all(b"hello").unwrap();
+
+            let mut link = Link::new();
+            link.relay(from_r.as_raw_fd(), to_w.as_raw_fd(), vec![0; 5]);
+            let (results, bufs) = link.submit().await;
+            let results = results.unwrap();
+            assert_eq!(results.iter().map(|r| *r.as_ref().unwrap()).collect::<Vec<_>>(), [5, 5]);
+            assert_eq!(bufs, [b"hello".to_vec()]);
+
+            let mut buf = [0; 5];
+            to_r.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, b"hello");
+        });
+    }
+
+    #[test]
+    fn dropped_in_flight() {
+        let ex = Executor::with_driver(DriverKind::IoUring).unwrap();
+        ex.block_on(|| async {
+            let (mut r, mut w) = pipe();
+
+            let mut link = Link::new();
+            link.read(r.as_raw_fd(), vec![0; 5]);
+            let mut submitted = link.submit();
+            assert!((&mut submitted).now_or_never().is_none());
+            // the read is cancelled, its buffer stays with the reactor until then
+            drop(submitted);
+            for _ in 0..10 {
+                yield_now().await;
+            }
+
+            w.write_all(b"later").unwrap();
+            let mut buf = [0; 5];
+            r.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, b"later");
+        });
+    }
+
+    #[test]
+    fn dropped_link_releases_its_slots() {
+        let ex = Executor::builder().driver(DriverKind::IoUring).fixed_files(2).build().unwrap();
+        ex.block_on(|| async {
+            let (r, _w) = pipe();
+
+            // the read holds the chain back, both opens never run
+            let mut link = Link::new();
+            link.read(r.as_raw_fd(), vec![0; 1]);
+            link.open("/dev/null");
+            link.open("/dev/null");
+            let mut submitted = link.submit();
+            assert!((&mut submitted).now_or_never().is_none());
+            drop(submitted);
+            for _ in 0..10 {
+                yield_now().await;
+            }
+
+            let mut link = Link::new();
+            link.open("/dev/null");
+            link.open("/dev/null");
+            let (results, _) = link.submit().await;
+            assert!(results.unwrap().iter().all(Result::is_ok));
+        });
+    }
+
+    #[test]
+    fn file_of_another_link() {
+        let ex = Executor::with_driver(DriverKind::IoUring).unwrap();
+        ex.block_on(|| async {
+            let mut first = Link::new();
+            let file = first.open("/dev/null");
+
+            let mut second = Link::new();
+            second.open("/dev/null");
+            second.fsync(file);
+            let (results, _) = second.submit().await;
+            assert_eq!(results.unwrap_err().kind(), ErrorKind::InvalidInput);
+        });
+    }
+
+    // let the executor reap completions
+    async fn yield_now() {
+        let mut yielded = false;
+        futures::future::poll_fn(|cx| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
@@ -0,0 +1,398 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt,
+    future::{poll_fn, Future},
+    io::{Error as IoError, Result as IoResult},
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    task::{Poll, Waker},
+    time::Duration,
+};
+
+use super::{AsyncFd, AsyncRead, AsyncWrite};
+
+/// A stream playing back a script, for testing protocol code without sockets.
+///
+/// ```
+/// use aruntime::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, MockStream};
+///
+/// futures::executor::block_on(async {
+///     let stream = MockStream::builder()
+///         .read(b"PING\r\n")
+///         .write(b"PONG\r\n")
+///         .read_error(std::io::ErrorKind::ConnectionReset.into())
+///         .build();
+///
+///     let mut buf = [0; 6];
+///     stream.read_exact(&mut buf).await.unwrap();
+///     stream.write_all(b"PONG\r\n").await.unwrap();
+///     assert!(stream.read(&mut buf).await.is_err());
+/// });
+/// ```
+///
+/// Reads take the script in order and wait while a write is due, so a reader and a writer
+/// task can share the stream. Reads resolve to 0 once the script is done. Writes go to the
+/// next expected write, skipping reads, and panic if the bytes differ. A wait delays the read
+/// or write of the action that follows it, or the end of the stream if nothing does. Dropping the stream panics if part of the script wasn't played.
+pub struct MockStream {
+    inner: RefCell<Script>,
+}
+
+/// Builds a [`MockStream`], from [`MockStream::builder`].
+#[derive(Default)]
+pub struct MockBuilder {
+    actions: VecDeque<(Duration, Action)>,
+    // waits not yet tied to an action
+    delay: Duration,
+}
+
+struct Script {
+    // each action with the delay before it's played
+    actions: VecDeque<(Duration, Action)>,
+    read_waker: Option<Waker>,
+}
+
+enum Action {
+    Read(Vec<u8>),
+    Write(Vec<u8>),
+    ReadError(IoError),
+    WriteError(IoError),
+}
+
+// what a read or write does next
+enum Step {
+    Done(IoResult<usize>),
+    Wait(Duration),
+}
+
+impl MockBuilder {
+    /// Bytes the stream hands to reads. They may take several reads.
+    pub fn read(self, data: &[u8]) -> Self {
+        if data.is_empty() {
+            return self;
+        }
+        self.push(Action::Read(data.to_vec()))
+    }
+
+    /// Bytes the stream expects to be written. They may come in several writes.
+    pub fn write(self, data: &[u8]) -> Self {
+        if data.is_empty() {
+            return self;
+        }
+        self.push(Action::Write(data.to_vec()))
+    }
+
+    pub fn read_error(self, error: IoError) -> Self {
+        self.push(Action::ReadError(error))
+    }
+
+    pub fn write_error(self, error: IoError) -> Self {
+        self.push(Action::WriteError(error))
+    }
+
+    /// Delay the next action by `duration`, only a read or write playing it waits.
+    pub fn wait(mut self, duration: Duration) -> Self {
+        self.delay += duration;
+        self
+    }
+
+    fn push(mut self, action: Action) -> Self {
+        self.actions.push_back((std::mem::take(&mut self.delay), action));
+        self
+    }
+
+    pub fn build(mut self) -> MockStream {
+        // a trailing wait delays the end of the stream
+        if !self.delay.is_zero() {
+            self = self.push(Action::Read(Vec::new()));
+        }
+
+        MockStream {
+            inner: RefCell::new(Script {
+                actions: self.actions,
+                read_waker: None,
+            }),
+        }
+    }
+}
+
+impl MockStream {
+    pub fn builder() -> MockBuilder {
+        MockBuilder::default()
+    }
+
+    async fn read_script(&self, buf: &mut [u8]) -> IoResult<usize> {
+        loop {
+            let step = poll_fn(|cx| {
+                let mut script = self.inner.borrow_mut();
+                let step = match script.actions.front_mut() {
+                    None => Step::Done(Ok(0)),
+                    Some((_, Action::Write(_) | Action::WriteError(_))) => {
+                        script.read_waker = Some(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                    Some((delay, _)) if !delay.is_zero() => Step::Wait(std::mem::take(delay)),
+                    Some((_, Action::Read(data))) => {
+                        let n = data.len().min(buf.len());
+                        buf[..n].copy_from_slice(&data[..n]);
+                        data.drain(..n);
+                        if data.is_empty() {
+                            script.actions.pop_front();
+                        }
+                        Step::Done(Ok(n))
+                    }
+                    Some((_, Action::ReadError(_))) => match script.actions.pop_front() {
+                        Some((_, Action::ReadError(e))) => Step::Done(Err(e)),
+                        _ => unreachable!(),
+                    },
+                };
+                Poll::Ready(step)
+            })
+            .await;
+
+            match step {
+                Step::Done(result) => return result,
+                Step::Wait(duration) => sleep(duration).await?,
+            }
+        }
+    }
+
+    async fn write_script(&self, buf: &[u8]) -> IoResult<usize> {
+        loop {
+            let step = {
+                let mut script = self.inner.borrow_mut();
+                let next = script
+                    .actions
+                    .iter()
+                    .position(|(_, action)| !matches!(action, Action::Read(_) | Action::ReadError(_)));
+                let Some(i) = next else {
+                    panic!("unexpected write of {buf:?}, the script expects no more writes");
+                };
+
+                let step = match &mut script.actions[i] {
+                    (delay, _) if !delay.is_zero() => Step::Wait(std::mem::take(delay)),
+                    (_, Action::Write(expected)) => {
+                        let n = expected.len().min(buf.len());
+                        assert_eq!(&buf[..n], &expected[..n], "unexpected write");
+                        expected.drain(..n);
+                        if expected.is_empty() {
+                            script.actions.remove(i);
+                        }
+                        Step::Done(Ok(n))
+                    }
+                    (_, Action::WriteError(_)) => match script.actions.remove(i) {
+                        Some((_, Action::WriteError(e))) => Step::Done(Err(e)),
+                        _ => unreachable!(),
+                    },
+                    (_, Action::Read(_) | Action::ReadError(_)) => unreachable!(),
+                };
+                // a read may be waiting for this write
+                if matches!(step, Step::Done(_)) {
+                    if let Some(waker) = script.read_waker.take() {
+                        waker.wake();
+                    }
+                }
+                step
+            };
+
+            match step {
+                Step::Done(result) => return result,
+                Step::Wait(duration) => sleep(duration).await?,
+            }
+        }
+    }
+}
+
+impl AsyncRead for MockStream {
+    fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        self.read_script(buf)
+    }
+}
+
+impl AsyncWrite for MockStream {
+    fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        self.write_script(buf)
+    }
+}
+
+impl Drop for MockStream {
+    fn drop(&mut self) {
+        let script = self.inner.get_mut();
+        if !script.actions.is_empty() && !std::thread::panicking() {
+            panic!("MockStream dropped before the end of its script: {:?}", script.actions);
+        }
+    }
+}
+
+impl fmt::Debug for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Read(data) => f.debug_tuple("Read").field(&String::from_utf8_lossy(data)).finish(),
+            Action::Write(data) => f.debug_tuple("Write").field(&String::from_utf8_lossy(data)).finish(),
+            Action::ReadError(e) => f.debug_tuple("ReadError").field(e).finish(),
+            Action::WriteError(e) => f.debug_tuple("WriteError").field(e).finish(),
+        }
+    }
+}
+
+// a timerfd waited on like any other fd, works with both drivers
+async fn sleep(duration: Duration) -> IoResult<()> {
+    if duration.is_zero() {
+        return Ok(());
+    }
+
+    let fd = unsafe { libc::timerfd_create(libc::CLOCK_MONOTONIC, libc::TFD_NONBLOCK | libc::TFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(IoError::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let spec = libc::itimerspec {
+        it_interval: libc::timespec { tv_sec: 0, tv_nsec: 0 },
+        it_value: libc::timespec {
+            tv_sec: duration.as_secs() as libc::time_t,
+            tv_nsec: duration.subsec_nanos() as libc::c_long,
+        },
+    };
+    if unsafe { libc::timerfd_settime(fd.as_raw_fd(), 0, &spec, std::ptr::null_mut()) } < 0 {
+        return Err(IoError::last_os_error());
+    }
+
+    AsyncFd::new(fd)?.readable().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::ErrorKind, time::Instant};
+
+    use futures::{executor::block_on, FutureExt};
+
+    use super::*;
+    use crate::{
+        executor::Executor,
+        io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    #[test]
+    fn plays_the_script_in_order() {
+        block_on(async {
+            let stream = MockStream::builder().read(b"PING\r\n").write(b"PONG\r\n").read(b"QUIT\r\n").build();
+
+            // reads may take the bytes in pieces
+            let mut buf = [0; 4];
+            assert_eq!(stream.read(&mut buf).await.unwrap(), 4);
+            assert_eq!(&buf, b"PING");
+            assert_eq!(stream.read(&mut buf).await.unwrap(), 2);
+
+            // so may writes
+            stream.write_all(b"PO").await.unwrap();
+            stream.write_all(b"NG\r\n").await.unwrap();
+
+            let mut rest = Vec::new();
+            stream.read_to_end(&mut rest).await.unwrap();
+            assert_eq!(rest, b"QUIT\r\n");
+        });
+    }
+
+    #[test]
+    fn reads_wait_for_expected_writes() {
+        block_on(async {
+            let stream = MockStream::builder().write(b"req").read(b"resp").build();
+            let mut buf = [0; 4];
+            assert!(stream.read(&mut buf).now_or_never().is_none());
+
+            stream.write_all(b"req").await.unwrap();
+            assert_eq!(stream.read(&mut buf).await.unwrap(), 4);
+        });
+    }
+
+    #[test]
+    fn writes_skip_pending_reads() {
+        block_on(async {
+            let stream = MockStream::builder().read(b"later").write(b"now").build();
+            stream.write_all(b"now").await.unwrap();
+
+            let mut buf = [0; 5];
+            stream.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"later");
+        });
+    }
+
+    #[test]
+    fn injected_errors() {
+        block_on(async {
+            let stream = MockStream::builder()
+                .read_error(ErrorKind::ConnectionReset.into())
+                .write_error(ErrorKind::BrokenPipe.into())
+                .build();
+
+            let mut buf = [0; 4];
+            assert_eq!(stream.read(&mut buf).await.unwrap_err().kind(), ErrorKind::ConnectionReset);
+            assert_eq!(stream.write(b"x").await.unwrap_err().kind(), ErrorKind::BrokenPipe);
+            assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+        });
+    }
+
+    #[test]
+    fn waits() {
+        Executor::new().block_on(|| async {
+            let stream = MockStream::builder().wait(Duration::from_millis(20)).read(b"x").build();
+            let start = Instant::now();
+            let mut buf = [0; 1];
+            stream.read_exact(&mut buf).await.unwrap();
+            assert!(start.elapsed() >= Duration::from_millis(20));
+        });
+    }
+
+    #[test]
+    fn a_write_doesnt_take_the_wait_of_a_read() {
+        Executor::new().block_on(|| async {
+            let stream = MockStream::builder()
+                .write(b"req")
+                .wait(Duration::from_millis(20))
+                .read(b"resp")
+                .wait(Duration::from_millis(20))
+                .build();
+            let start = Instant::now();
+
+            let read = async {
+                let mut buf = [0; 4];
+                stream.read_exact(&mut buf).await.unwrap();
+                assert!(start.elapsed() >= Duration::from_millis(20));
+                // the trailing wait delays the end of the stream
+                assert_eq!(stream.read(&mut buf).await.unwrap(), 0);
+                assert!(start.elapsed() >= Duration::from_millis(40));
+            };
+            let write = async {
+                stream.write_all(b"req").await.unwrap();
+                assert!(start.elapsed() < Duration::from_millis(20));
+            };
+            futures::join!(read, write);
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "unexpected write")]
+    fn mismatched_write() {
+        block_on(async {
+            let stream = MockStream::builder().write(b"abc").build();
+            let _ = stream.write(b"abd").await;
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "no more writes")]
+    fn write_past_the_script() {
+        block_on(async {
+            let stream = MockStream::builder().read(b"abc").build();
+            let _ = stream.write(b"x").await;
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "before the end of its script")]
+    fn unplayed_script() {
+        drop(MockStream::builder().read(b"unread").build());
+    }
+}
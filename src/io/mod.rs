@@ -1,28 +1,66 @@
 use std::{
-    cell::RefCell,
     future::Future,
     io::{Result as IoResult, Error as IoError, ErrorKind},
-    rc::{Rc, Weak},
     task::{Context, Poll},
 };
 
-use crate::reactor::{get_reactor, Reactor};
+use crate::{
+    buf::{BufRing, FixedBuf, ProvidedBuf},
+    reactor::{FdTarget, ReactorRef},
+};
+
+mod async_fd;
+mod async_io;
+mod buffered;
+mod compat;
+mod copy;
+mod duplex;
+mod ext;
+mod link;
+mod mock;
+mod pipe;
+pub mod raw;
+mod shared_fd;
+mod splice;
+mod split;
+mod stdio;
+mod vectored;
+pub use async_fd::{AsyncFd, Readiness, ReadyGuard};
+pub use async_io::{AsyncRead, AsyncWrite};
+pub use buffered::{BufReader, BufWriter};
+pub use compat::Compat;
+pub(crate) use compat::detach;
+pub use copy::{copy, copy_bidirectional};
+pub use duplex::{duplex, DuplexStream};
+pub use ext::{AsyncReadExt, AsyncWriteExt};
+pub use link::{Link, LinkFd, LinkFuture};
+pub use mock::{MockBuilder, MockStream};
+pub use pipe::{pipe, PipeReader, PipeWriter};
+pub(crate) use shared_fd::SharedFd;
+pub use splice::{splice, tee};
+pub use split::{split, ReadHalf, WriteHalf};
+pub use stdio::{stderr, stdin, stdout, Stderr, Stdin, Stdout};
+pub use vectored::{ReadVectored, WriteVectored};
+pub(crate) use vectored::{read_owned, write_all_vectored, write_owned};
 
 pub struct AsyncReader<'a> {
-    fd: i32,
+    fd: FdTarget,
     buf: &'a mut [u8],
     token: Option<u64>,
-    reactor: Weak<RefCell<Reactor>>,
+    reactor: ReactorRef,
 }
 
 impl<'a> AsyncReader<'a> {
     pub fn new(fd: i32, buf: &'a mut [u8]) -> Self {
-        let reactor = get_reactor();
+        Self::with_target(FdTarget::Raw(fd), buf)
+    }
+
+    pub(crate) fn with_target(fd: FdTarget, buf: &'a mut [u8]) -> Self {
         Self {
             fd,
             buf,
             token: None,
-            reactor: Rc::downgrade(&reactor),
+            reactor: ReactorRef::default(),
         }
     }
 }
@@ -31,9 +69,15 @@ impl<'a> Future for AsyncReader<'a> {
     type Output = IoResult<usize>;
 
     fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let reactor = self.reactor.upgrade().unwrap();
+        let reactor = self.reactor.get()?;
 
-        if self.token.is_none() {
+        if let Some(token) = self.token {
+            if let Some(result) = reactor.borrow_mut().take_token_result(token) {
+                Poll::Ready(completion_result(result))
+            } else {
+                Poll::Pending
+            }
+        } else {
             let token = reactor.borrow_mut().read(
                 self.fd,
                 cx,
@@ -44,41 +88,28 @@ impl<'a> Future for AsyncReader<'a> {
             self.token = Some(token);
 
             Poll::Pending
-        } else {
-            if let Some(result) = reactor.borrow_mut().take_token_result(self.token.unwrap()) {
-                if result < 0 {
-                    let err_code = -result;
-                    match err_code {
-                        libc::EAGAIN => {
-                            Poll::Ready(Err(IoError::new(ErrorKind::WouldBlock, "Would block")))
-                        }
-                        _ => Poll::Ready(Err(std::io::Error::from_raw_os_error(err_code))),
-                    }
-                } else {
-                    Poll::Ready(Ok(result as usize))
-                }
-            } else {
-                Poll::Pending
-            }
         }
     }
 }
 
 pub struct AsyncWriter<'a> {
-    fd: i32,
+    fd: FdTarget,
     buf: &'a [u8],
     token: Option<u64>,
-    reactor: Weak<RefCell<Reactor>>,
+    reactor: ReactorRef,
 }
 
 impl<'a> AsyncWriter<'a> {
     pub fn new(fd: i32, buf: &'a [u8]) -> Self {
-        let reactor = get_reactor();
+        Self::with_target(FdTarget::Raw(fd), buf)
+    }
+
+    pub(crate) fn with_target(fd: FdTarget, buf: &'a [u8]) -> Self {
         Self {
             fd,
             buf,
             token: None,
-            reactor: Rc::downgrade(&reactor),
+            reactor: ReactorRef::default(),
         }
     }
 }
@@ -87,9 +118,15 @@ impl<'a> Future for AsyncWriter<'a> {
     type Output = IoResult<usize>;
 
     fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let reactor = self.reactor.upgrade().unwrap();
+        let reactor = self.reactor.get()?;
 
-        if self.token.is_none() {
+        if let Some(token) = self.token {
+            if let Some(result) = reactor.borrow_mut().take_token_result(token) {
+                Poll::Ready(completion_result(result))
+            } else {
+                Poll::Pending
+            }
+        } else {
             let token = reactor.borrow_mut().write(
                 self.fd,
                 cx,
@@ -99,22 +136,216 @@ impl<'a> Future for AsyncWriter<'a> {
             self.token = Some(token);
 
             Poll::Pending
+        }
+    }
+}
+
+/// Read into a [`FixedBuf`], resolving to the result and the buffer.
+///
+/// The future owns the buffer, if dropped in flight it goes to the reactor until the kernel
+/// is done with it.
+pub struct AsyncFixedReader {
+    fd: FdTarget,
+    buf: Option<FixedBuf>,
+    offset: u64,
+    token: Option<u64>,
+    reactor: ReactorRef,
+}
+
+impl AsyncFixedReader {
+    pub fn new(fd: i32, buf: FixedBuf, offset: u64) -> Self {
+        Self::with_target(FdTarget::Raw(fd), buf, offset)
+    }
+
+    pub(crate) fn with_target(fd: FdTarget, buf: FixedBuf, offset: u64) -> Self {
+        Self {
+            fd,
+            buf: Some(buf),
+            offset,
+            token: None,
+            reactor: ReactorRef::default(),
+        }
+    }
+}
+
+impl Future for AsyncFixedReader {
+    type Output = (IoResult<usize>, FixedBuf);
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let reactor = match self.reactor.get() {
+            Ok(reactor) => reactor,
+            Err(e) => return Poll::Ready((Err(e), self.buf.take().unwrap())),
+        };
+
+        if let Some(token) = self.token {
+            if let Some(result) = reactor.borrow_mut().take_token_result(token) {
+                self.token = None;
+                let mut buf = self.buf.take().unwrap();
+                let result = completion_result(result);
+                if let Ok(n) = result {
+                    buf.set_len(n);
+                }
+                Poll::Ready((result, buf))
+            } else {
+                Poll::Pending
+            }
+        } else {
+            let (fd, offset) = (self.fd, self.offset);
+            let buf = self.buf.as_mut().unwrap();
+            let (ptr, capacity, index) = (buf.as_mut_ptr(), buf.capacity(), buf.index());
+            let token = reactor.borrow_mut().read_fixed(fd, cx, ptr, capacity, index, offset);
+            self.token = Some(token);
+
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for AsyncFixedReader {
+    fn drop(&mut self) {
+        if let (Some(token), Some(buf), Some(reactor)) = (self.token, self.buf.take(), self.reactor.bound()) {
+            reactor.borrow_mut().cancel_and_keep(token, Box::new(buf));
+        }
+    }
+}
+
+/// Write the content of a [`FixedBuf`], resolving to the result and the buffer.
+///
+/// Owns the buffer like [`AsyncFixedReader`].
+pub struct AsyncFixedWriter {
+    fd: FdTarget,
+    buf: Option<FixedBuf>,
+    offset: u64,
+    token: Option<u64>,
+    reactor: ReactorRef,
+}
+
+impl AsyncFixedWriter {
+    pub fn new(fd: i32, buf: FixedBuf, offset: u64) -> Self {
+        Self::with_target(FdTarget::Raw(fd), buf, offset)
+    }
+
+    pub(crate) fn with_target(fd: FdTarget, buf: FixedBuf, offset: u64) -> Self {
+        Self {
+            fd,
+            buf: Some(buf),
+            offset,
+            token: None,
+            reactor: ReactorRef::default(),
+        }
+    }
+}
+
+impl Future for AsyncFixedWriter {
+    type Output = (IoResult<usize>, FixedBuf);
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let reactor = match self.reactor.get() {
+            Ok(reactor) => reactor,
+            Err(e) => return Poll::Ready((Err(e), self.buf.take().unwrap())),
+        };
+
+        if let Some(token) = self.token {
+            if let Some(result) = reactor.borrow_mut().take_token_result(token) {
+                self.token = None;
+                Poll::Ready((completion_result(result), self.buf.take().unwrap()))
+            } else {
+                Poll::Pending
+            }
         } else {
-            if let Some(result) = reactor.borrow_mut().take_token_result(self.token.unwrap()) {
-                if result < 0 {
-                    let err_code = -result;
-                    match err_code {
-                        libc::EAGAIN => {
-                            Poll::Ready(Err(IoError::new(ErrorKind::WouldBlock, "Would block")))
-                        }
-                        _ => Poll::Ready(Err(std::io::Error::from_raw_os_error(err_code))),
-                    }
-                } else {
-                    Poll::Ready(Ok(result as usize))
+            let buf = self.buf.as_ref().unwrap();
+            let token = reactor.borrow_mut().write_fixed(self.fd, cx, buf.as_ptr(), buf.len(), buf.index(), self.offset);
+            self.token = Some(token);
+
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for AsyncFixedWriter {
+    fn drop(&mut self) {
+        if let (Some(token), Some(buf), Some(reactor)) = (self.token, self.buf.take(), self.reactor.bound()) {
+            reactor.borrow_mut().cancel_and_keep(token, Box::new(buf));
+        }
+    }
+}
+
+/// Receive into a buffer the kernel picks from a [`BufRing`] once data arrives.
+pub struct AsyncRecvBuf<'a> {
+    fd: FdTarget,
+    ring: &'a BufRing,
+    token: Option<u64>,
+    reactor: ReactorRef,
+}
+
+impl<'a> AsyncRecvBuf<'a> {
+    pub fn new(fd: i32, ring: &'a BufRing) -> Self {
+        Self::with_target(FdTarget::Raw(fd), ring)
+    }
+
+    pub(crate) fn with_target(fd: FdTarget, ring: &'a BufRing) -> Self {
+        Self {
+            fd,
+            ring,
+            token: None,
+            reactor: ReactorRef::default(),
+        }
+    }
+}
+
+impl<'a> Future for AsyncRecvBuf<'a> {
+    type Output = IoResult<ProvidedBuf>;
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let reactor = self.reactor.get()?;
+
+        if let Some(token) = self.token {
+            if let Some((result, flags)) = reactor.borrow_mut().take_token_completion(token) {
+                self.token = None;
+                let buf = io_uring::cqueue::buffer_select(flags).map(|bid| self.ring.take(bid, result.max(0) as usize));
+                match (completion_result(result), buf) {
+                    (Err(e), _) => Poll::Ready(Err(e)),
+                    (Ok(0), _) => Poll::Ready(Err(IoError::from(ErrorKind::UnexpectedEof))),
+                    (Ok(_), Some(buf)) => Poll::Ready(Ok(buf)),
+                    (Ok(_), None) => Poll::Ready(Err(IoError::other("no buffer selected for the receive"))),
                 }
             } else {
                 Poll::Pending
             }
+        } else {
+            let (fd, bgid, len) = (self.fd, self.ring.bgid(), self.ring.buf_len());
+            let token = reactor.borrow_mut().recv_buf_select(fd, cx, bgid, len);
+            self.token = Some(token);
+
+            Poll::Pending
+        }
+    }
+}
+
+impl<'a> Drop for AsyncRecvBuf<'a> {
+    fn drop(&mut self) {
+        let (Some(token), Some(reactor)) = (self.token, self.reactor.bound()) else {
+            return;
+        };
+        // give back the buffer the kernel may still pick
+        let ring = self.ring.clone();
+        reactor.borrow_mut().orphan_token(token, move |result, flags| {
+            if let Some(bid) = io_uring::cqueue::buffer_select(flags) {
+                drop(ring.take(bid, result.max(0) as usize));
+            }
+        });
+    }
+}
+
+/// Convert the result of a CQE into the number of bytes transferred.
+pub(crate) fn completion_result(result: i32) -> IoResult<usize> {
+    if result < 0 {
+        let err_code = -result;
+        match err_code {
+            libc::EAGAIN => Err(IoError::new(ErrorKind::WouldBlock, "Would block")),
+            _ => Err(std::io::Error::from_raw_os_error(err_code)),
         }
+    } else {
+        Ok(result as usize)
     }
 }
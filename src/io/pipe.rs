@@ -0,0 +1,128 @@
+use std::{
+    future::Future,
+    io::{Error as IoError, Result as IoResult},
+    os::fd::{AsRawFd, IntoRawFd, OwnedFd, RawFd},
+};
+
+use super::{read_owned, write_owned, AsyncRead, AsyncReader, AsyncWrite, AsyncWriter, SharedFd};
+
+/// Create a pipe, the read end first.
+///
+/// Both ends can also be made from the `OwnedFd` of an existing pipe, e.g. the stdio of a child process.
+pub fn pipe() -> IoResult<(PipeReader, PipeWriter)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+        return Err(IoError::last_os_error());
+    }
+
+    Ok((
+        PipeReader {
+            fd: SharedFd::new(fds[0]),
+        },
+        PipeWriter {
+            fd: SharedFd::new(fds[1]),
+        },
+    ))
+}
+
+/// The read end of a pipe.
+pub struct PipeReader {
+    fd: SharedFd,
+}
+
+impl PipeReader {
+    /// Read into `buf`, resolving to 0 once every write end is closed.
+    pub fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        AsyncReader::with_target(self.fd.target(), buf)
+    }
+
+    pub async fn close(self) -> IoResult<()> {
+        self.fd.close().await
+    }
+}
+
+impl AsyncRead for PipeReader {
+    fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        PipeReader::read(self, buf)
+    }
+
+    fn read_owned<B: AsMut<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        read_owned(self.fd.target(), buf)
+    }
+}
+
+impl From<OwnedFd> for PipeReader {
+    fn from(fd: OwnedFd) -> Self {
+        Self {
+            fd: SharedFd::new(fd.into_raw_fd()),
+        }
+    }
+}
+
+impl AsRawFd for PipeReader {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// The write end of a pipe, the reader sees the end of the stream once it is closed.
+pub struct PipeWriter {
+    fd: SharedFd,
+}
+
+impl PipeWriter {
+    /// Write from `buf`, the count may be short when the pipe is nearly full.
+    pub fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        AsyncWriter::with_target(self.fd.target(), buf)
+    }
+
+    pub async fn close(self) -> IoResult<()> {
+        self.fd.close().await
+    }
+}
+
+impl AsyncWrite for PipeWriter {
+    fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        PipeWriter::write(self, buf)
+    }
+
+    fn write_owned<B: AsRef<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        write_owned(self.fd.target(), buf)
+    }
+}
+
+impl From<OwnedFd> for PipeWriter {
+    fn from(fd: OwnedFd) -> Self {
+        Self {
+            fd: SharedFd::new(fd.into_raw_fd()),
+        }
+    }
+}
+
+impl AsRawFd for PipeWriter {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        executor::Executor,
+        io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    #[test]
+    fn eof_once_the_writer_is_closed() {
+        Executor::new().block_on(|| async {
+            let (r, w) = pipe().unwrap();
+            w.write_all(b"hello").await.unwrap();
+            w.close().await.unwrap();
+
+            let mut got = String::new();
+            r.read_to_string(&mut got).await.unwrap();
+            assert_eq!(got, "hello");
+        });
+    }
+}
@@ -0,0 +1,164 @@
+//! Submission of SQEs the crate doesn't wrap, e.g. `IORING_OP_URING_CMD` or `IORING_OP_FADVISE`.
+//!
+//! The runtime owns `user_data` and the token and waker bookkeeping, the rest of the SQE is
+//! passed to the kernel as built. Everything it points to is the caller's responsibility.
+
+use std::{
+    future::Future,
+    io::Result as IoResult,
+    task::{Context, Poll},
+};
+
+use io_uring::squeue;
+
+use crate::reactor::{FdTarget, ReactorRef};
+
+use super::completion_result;
+
+/// Result and flags of a CQE.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Completion {
+    pub result: i32,
+    pub flags: u32,
+}
+
+impl Completion {
+    /// The result as a byte count, or the error a negative result stands for.
+    pub fn io_result(&self) -> IoResult<usize> {
+        completion_result(self.result)
+    }
+}
+
+/// Submit `sqe` and wait for its completion.
+///
+/// Multishot requests only report their last completion. The future cancels the request
+/// when dropped, but the kernel may still use it until it completes.
+///
+/// # Safety
+///
+/// Every buffer, fd and struct the SQE refers to must stay valid until the request
+/// completes, even if the future is dropped before. `IOSQE_IO_LINK` and `IOSQE_IO_HARDLINK`
+/// must not be set, use [`submit_link`] for chains.
+pub unsafe fn submit(sqe: squeue::Entry) -> RawOp {
+    RawOp {
+        sqe: Some(sqe),
+        token: None,
+        reactor: ReactorRef::default(),
+    }
+}
+
+/// Submit `sqes` as one chain linked with `IOSQE_IO_LINK`, or `IOSQE_IO_HARDLINK` when `hard`,
+/// and wait for all of them. The runtime sets the link flags.
+///
+/// # Safety
+///
+/// As for [`submit`], for every SQE of the chain.
+pub unsafe fn submit_link(sqes: Vec<squeue::Entry>, hard: bool) -> RawLink {
+    RawLink {
+        sqes,
+        hard,
+        tokens: Vec::new(),
+        completions: Vec::new(),
+        reactor: ReactorRef::default(),
+    }
+}
+
+// raw requests aren't tied to any fd the runtime knows about
+const NO_FD: FdTarget = FdTarget::Raw(-1);
+
+/// Future returned by [`submit`].
+pub struct RawOp {
+    sqe: Option<squeue::Entry>,
+    token: Option<u64>,
+    reactor: ReactorRef,
+}
+
+impl Future for RawOp {
+    type Output = IoResult<Completion>;
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let reactor = self.reactor.get()?;
+        let mut reactor = reactor.borrow_mut();
+
+        if let Some(sqe) = self.sqe.take() {
+            self.token = Some(reactor.submit_raw(NO_FD, cx, sqe));
+            return Poll::Pending;
+        }
+
+        let token = self.token.unwrap();
+        match reactor.take_token_completion(token) {
+            Some((result, flags)) => {
+                self.token = None;
+                Poll::Ready(Ok(Completion { result, flags }))
+            }
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for RawOp {
+    fn drop(&mut self) {
+        if let (Some(token), Some(reactor)) = (self.token, self.reactor.bound()) {
+            reactor.borrow_mut().cancel_token(token);
+        }
+    }
+}
+
+/// Future returned by [`submit_link`], resolving to one completion per SQE.
+pub struct RawLink {
+    sqes: Vec<squeue::Entry>,
+    hard: bool,
+    tokens: Vec<u64>,
+    completions: Vec<Option<Completion>>,
+    reactor: ReactorRef,
+}
+
+impl Future for RawLink {
+    type Output = IoResult<Vec<Completion>>;
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let reactor = self.reactor.get()?;
+        let mut reactor = reactor.borrow_mut();
+
+        if !self.sqes.is_empty() {
+            let sqes = std::mem::take(&mut self.sqes).into_iter().map(|sqe| (NO_FD, sqe)).collect();
+            let hard = self.hard;
+            return match reactor.link(cx, sqes, hard) {
+                Ok(tokens) => {
+                    self.completions = vec![None; tokens.len()];
+                    self.tokens = tokens;
+                    Poll::Pending
+                }
+                Err(e) => Poll::Ready(Err(e)),
+            };
+        }
+
+        for i in 0..self.tokens.len() {
+            if self.completions[i].is_none() {
+                self.completions[i] = reactor
+                    .take_token_completion(self.tokens[i])
+                    .map(|(result, flags)| Completion { result, flags });
+            }
+        }
+        if self.completions.iter().all(Option::is_some) {
+            self.tokens.clear();
+            Poll::Ready(Ok(self.completions.drain(..).map(Option::unwrap).collect()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for RawLink {
+    fn drop(&mut self) {
+        let Some(reactor) = self.reactor.bound() else {
+            return;
+        };
+        let mut reactor = reactor.borrow_mut();
+        for (i, &token) in self.tokens.iter().enumerate() {
+            if self.completions[i].is_none() {
+                reactor.cancel_token(token);
+            }
+        }
+    }
+}
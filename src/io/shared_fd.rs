@@ -0,0 +1,179 @@
+use std::{
+    cell::Cell,
+    future::Future,
+    io::Result as IoResult,
+    os::fd::{AsRawFd, RawFd},
+    rc::Rc,
+    task::{Context, Poll},
+};
+
+use crate::reactor::{FdTarget, ReactorRef};
+
+/// An owned file descriptor shared by all resource types.
+///
+/// The fd is closed through `IORING_OP_CLOSE` once the last handle is dropped,
+/// after cancelling the operations still in flight on it. It may also live in the
+/// ring's fixed file table, either registered from a regular fd or as a direct
+/// descriptor which has no regular fd at all.
+#[derive(Clone)]
+pub(crate) struct SharedFd {
+    inner: Rc<Inner>,
+}
+
+struct Inner {
+    // -1 once the fd has been closed explicitly, or for direct descriptors
+    fd: Cell<RawFd>,
+    // slot in the fixed file table
+    fixed: Cell<Option<u32>>,
+    reactor: ReactorRef,
+}
+
+impl SharedFd {
+    pub(crate) fn new(fd: RawFd) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                fd: Cell::new(fd),
+                fixed: Cell::new(None),
+                reactor: ReactorRef::default(),
+            }),
+        }
+    }
+
+    /// Wrap a direct descriptor living in the fixed file table `slot` of `reactor`.
+    pub(crate) fn new_direct(slot: u32, reactor: ReactorRef) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                fd: Cell::new(-1),
+                fixed: Cell::new(Some(slot)),
+                reactor,
+            }),
+        }
+    }
+
+    /// What operations should be issued against, the fixed slot when there is one.
+    pub(crate) fn target(&self) -> FdTarget {
+        // ops get issued against the fd, bind it so they are cancelled on close and the
+        // close goes through the ring. Nothing to bind to outside a runtime.
+        let _ = self.inner.reactor.get();
+        match self.inner.fixed.get() {
+            Some(slot) => FdTarget::Fixed(slot),
+            None => FdTarget::Raw(self.inner.fd.get()),
+        }
+    }
+
+    pub(crate) fn is_fixed(&self) -> bool {
+        self.inner.fixed.get().is_some()
+    }
+
+    /// Register the fd into the fixed file table, following operations use the slot.
+    pub(crate) fn register_fixed(&self) -> IoResult<()> {
+        if self.is_fixed() {
+            return Ok(());
+        }
+
+        let slot = self.inner.reactor.get()?.borrow_mut().register_file(self.inner.fd.get())?;
+        self.inner.fixed.set(Some(slot));
+
+        Ok(())
+    }
+
+    /// Close the fd and wait for the result.
+    ///
+    /// If other handles to the fd are still alive only this handle is dropped,
+    /// and the fd is closed when the last one goes away.
+    pub(crate) async fn close(self) -> IoResult<()> {
+        let inner = match Rc::try_unwrap(self.inner) {
+            Ok(inner) => inner,
+            Err(_) => return Ok(()),
+        };
+        let fd = inner.fd.replace(-1);
+        let fixed = inner.fixed.take();
+
+        match inner.reactor.get().ok() {
+            Some(reactor) => {
+                if let Some(slot) = fixed {
+                    let mut reactor = reactor.borrow_mut();
+                    reactor.unregister_fd(FdTarget::Fixed(slot));
+                    reactor.release_file_slot(slot);
+                }
+                if fd < 0 {
+                    // a direct descriptor is closed by clearing its slot
+                    return Ok(());
+                }
+                reactor.borrow_mut().unregister_fd(fd);
+                Closer {
+                    fd,
+                    token: None,
+                    reactor: inner.reactor.clone(),
+                }
+                .await
+            }
+            None if fd < 0 => Ok(()),
+            None => {
+                if unsafe { libc::close(fd) } < 0 {
+                    Err(std::io::Error::last_os_error())
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+impl AsRawFd for SharedFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.fd.get()
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        let fd = self.fd.get();
+
+        match self.reactor.get().ok() {
+            Some(reactor) => {
+                let mut reactor = reactor.borrow_mut();
+                if let Some(slot) = self.fixed.get() {
+                    reactor.unregister_fd(FdTarget::Fixed(slot));
+                    reactor.release_file_slot(slot);
+                }
+                if fd >= 0 {
+                    reactor.unregister_fd(fd);
+                    reactor.close_detached(fd);
+                }
+            }
+            // dropped outside a runtime, or the one it was used on is gone
+            None if fd >= 0 => unsafe {
+                libc::close(fd);
+            },
+            None => {}
+        }
+    }
+}
+
+struct Closer {
+    fd: RawFd,
+    token: Option<u64>,
+    reactor: ReactorRef,
+}
+
+impl Future for Closer {
+    type Output = IoResult<()>;
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let reactor = self.reactor.get()?;
+
+        if let Some(token) = self.token {
+            match reactor.borrow_mut().take_token_result(token) {
+                Some(result) if result < 0 => Poll::Ready(Err(std::io::Error::from_raw_os_error(-result))),
+                Some(_) => Poll::Ready(Ok(())),
+                None => Poll::Pending,
+            }
+        } else {
+            let token = reactor.borrow_mut().close(self.fd, cx);
+            self.token = Some(token);
+
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    future::Future,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    os::fd::{AsRawFd, OwnedFd, RawFd},
+    task::{Context, Poll},
+};
+
+use io_uring::opcode;
+
+use crate::reactor::{current_reactor, ReactorRef};
+
+use super::completion_result;
+
+/// Move up to `len` bytes from `from` to `to` through an internal pipe, without copying
+/// them through userspace. Stops early at end of file, and returns the number of bytes moved.
+///
+/// Either end may be a file, a socket or a pipe, files are read and written at their
+/// current position.
+pub async fn splice(from: &impl AsRawFd, to: &impl AsRawFd, len: usize) -> IoResult<usize> {
+    let (from, to) = (from.as_raw_fd(), to.as_raw_fd());
+    let pipe = Pipe::take()?;
+
+    let mut moved = 0;
+    while moved < len {
+        let n = SpliceOp::splice(from, pipe.write(), len - moved).await?;
+        if n == 0 {
+            break;
+        }
+        drain(pipe.read(), to, n).await?;
+        moved += n;
+    }
+
+    pipe.recycle();
+    Ok(moved)
+}
+
+/// Like [`splice`], but every byte read from `from` is written to both `to` and `copy`,
+/// duplicated with `IORING_OP_TEE` between two internal pipes.
+pub async fn tee(from: &impl AsRawFd, to: &impl AsRawFd, copy: &impl AsRawFd, len: usize) -> IoResult<usize> {
+    let (from, to, copy) = (from.as_raw_fd(), to.as_raw_fd(), copy.as_raw_fd());
+    let main = Pipe::take()?;
+    let side = Pipe::take()?;
+
+    let mut moved = 0;
+    while moved < len {
+        let n = SpliceOp::splice(from, main.write(), len - moved).await?;
+        if n == 0 {
+            break;
+        }
+        // both pipes have the same capacity and `side` is empty, so it takes everything
+        let teed = SpliceOp::tee(main.read(), side.write(), n).await?;
+        if teed != n {
+            return Err(IoError::other("short tee"));
+        }
+        let (main_drained, side_drained) = futures::join!(drain(main.read(), to, n), drain(side.read(), copy, n));
+        main_drained?;
+        side_drained?;
+        moved += n;
+    }
+
+    main.recycle();
+    side.recycle();
+    Ok(moved)
+}
+
+/// Splice exactly `n` bytes out of the pipe `pipe` into `to`.
+async fn drain(pipe: RawFd, to: RawFd, mut n: usize) -> IoResult<()> {
+    while n > 0 {
+        match SpliceOp::splice(pipe, to, n).await? {
+            0 => return Err(IoError::from(ErrorKind::WriteZero)),
+            written => n -= written,
+        }
+    }
+    Ok(())
+}
+
+/// A pipe borrowed from the reactor, closed instead of given back if it may still hold data.
+struct Pipe {
+    fds: Option<(OwnedFd, OwnedFd)>,
+    reactor: ReactorRef,
+}
+
+impl Pipe {
+    fn take() -> IoResult<Self> {
+        let reactor = current_reactor()?;
+        let fds = reactor.borrow_mut().take_pipe()?;
+
+        Ok(Self {
+            fds: Some(fds),
+            reactor: ReactorRef::bound_to(&reactor),
+        })
+    }
+
+    fn read(&self) -> RawFd {
+        self.fds.as_ref().unwrap().0.as_raw_fd()
+    }
+
+    fn write(&self) -> RawFd {
+        self.fds.as_ref().unwrap().1.as_raw_fd()
+    }
+
+    /// The pipe was drained, let the next splice reuse it.
+    fn recycle(mut self) {
+        if let (Some(fds), Some(reactor)) = (self.fds.take(), self.reactor.bound()) {
+            reactor.borrow_mut().recycle_pipe(fds);
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum SpliceKind {
+    Splice,
+    Tee,
+}
+
+struct SpliceOp {
+    kind: SpliceKind,
+    fd_in: RawFd,
+    fd_out: RawFd,
+    len: u32,
+    token: Option<u64>,
+    reactor: ReactorRef,
+}
+
+impl SpliceOp {
+    fn splice(fd_in: RawFd, fd_out: RawFd, len: usize) -> Self {
+        Self::new(SpliceKind::Splice, fd_in, fd_out, len)
+    }
+
+    fn tee(fd_in: RawFd, fd_out: RawFd, len: usize) -> Self {
+        Self::new(SpliceKind::Tee, fd_in, fd_out, len)
+    }
+
+    fn new(kind: SpliceKind, fd_in: RawFd, fd_out: RawFd, len: usize) -> Self {
+        Self {
+            kind,
+            fd_in,
+            fd_out,
+            len: len.min(u32::MAX as usize) as u32,
+            token: None,
+            reactor: ReactorRef::default(),
+        }
+    }
+}
+
+impl Future for SpliceOp {
+    type Output = IoResult<usize>;
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let reactor = self.reactor.get()?;
+        let mut reactor = reactor.borrow_mut();
+
+        if let Some(token) = self.token {
+            match reactor.take_token_result(token) {
+                Some(result) => {
+                    self.token = None;
+                    Poll::Ready(completion_result(result))
+                }
+                None => Poll::Pending,
+            }
+        } else {
+            let (code, name) = match self.kind {
+                SpliceKind::Splice => (opcode::Splice::CODE, "splice"),
+                SpliceKind::Tee => (opcode::Tee::CODE, "tee"),
+            };
+            let capabilities = reactor.capabilities();
+            if let Err(e) = capabilities.require(capabilities.supports_opcode(code), name) {
+                return Poll::Ready(Err(e));
+            }
+
+            let token = match self.kind {
+                SpliceKind::Splice => reactor.splice(cx, self.fd_in, self.fd_out, self.len),
+                SpliceKind::Tee => reactor.tee(cx, self.fd_in, self.fd_out, self.len),
+            };
+            self.token = Some(token);
+
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for SpliceOp {
+    fn drop(&mut self) {
+        if let (Some(token), Some(reactor)) = (self.token, self.reactor.bound()) {
+            reactor.borrow_mut().cancel_token(token);
+        }
+    }
+}
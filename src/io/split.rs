@@ -0,0 +1,98 @@
+use std::{future::Future, io::Result as IoResult, rc::Rc};
+
+use super::{AsyncRead, AsyncWrite};
+
+/// Split anything readable and writable into halves which can be used from different tasks.
+///
+/// Reads and writes take `&self`, so the halves just share the value.
+pub fn split<T: AsyncRead + AsyncWrite>(stream: T) -> (ReadHalf<T>, WriteHalf<T>) {
+    let stream = Rc::new(stream);
+    (ReadHalf { inner: stream.clone() }, WriteHalf { inner: stream })
+}
+
+/// The read half returned by [`split`].
+pub struct ReadHalf<T> {
+    inner: Rc<T>,
+}
+
+/// The write half returned by [`split`].
+pub struct WriteHalf<T> {
+    inner: Rc<T>,
+}
+
+impl<T> ReadHalf<T> {
+    /// Put the value back together.
+    ///
+    /// # Panics
+    ///
+    /// If `write` comes from another `split`.
+    pub fn unsplit(self, write: WriteHalf<T>) -> T {
+        assert!(Rc::ptr_eq(&self.inner, &write.inner), "unsplit of halves from different splits");
+        drop(write);
+        Rc::into_inner(self.inner).unwrap()
+    }
+}
+
+impl<T: AsyncRead> AsyncRead for ReadHalf<T> {
+    fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        self.inner.read(buf)
+    }
+
+    fn read_owned<B: AsMut<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        self.inner.read_owned(buf)
+    }
+}
+
+impl<T: AsyncWrite> AsyncWrite for WriteHalf<T> {
+    fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        self.inner.write(buf)
+    }
+
+    fn write_owned<B: AsRef<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        self.inner.write_owned(buf)
+    }
+
+    fn flush(&self) -> impl Future<Output = IoResult<()>> + '_ {
+        self.inner.flush()
+    }
+
+    fn shutdown(&self) -> impl Future<Output = IoResult<()>> + '_ {
+        self.inner.shutdown()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::io::{copy, duplex, AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn halves_used_at_once() {
+        block_on(async {
+            // far more than the duplex holds, so writing only completes while reading
+            let (a, b) = duplex(8);
+            let (read, write) = split(a);
+            let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+
+            let echo = async {
+                copy(&b, &b).await.unwrap();
+                AsyncWrite::shutdown(&b).await.unwrap();
+            };
+            let send = async {
+                write.write_all(&data).await.unwrap();
+                write.shutdown().await.unwrap();
+            };
+            let receive = async {
+                let mut got = Vec::new();
+                read.read_to_end(&mut got).await.unwrap();
+                got
+            };
+            let ((), (), got) = futures::join!(echo, send, receive);
+            assert_eq!(got, data);
+
+            read.unsplit(write);
+        });
+    }
+}
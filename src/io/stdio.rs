@@ -0,0 +1,164 @@
+use std::{
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    os::fd::{AsRawFd, RawFd},
+};
+
+use super::{read_owned, write_owned, AsyncFd, AsyncRead, AsyncReader, AsyncWrite, AsyncWriter};
+use crate::reactor::FdTarget;
+
+/// Handle to the process's standard input, from [`stdin`]. Reads aren't buffered, wrap it in
+/// a [`BufReader`](super::BufReader) for lines.
+pub struct Stdin(StdioFd);
+
+/// Handle to the process's standard output, from [`stdout`]. Nothing is buffered in userspace.
+pub struct Stdout(StdioFd);
+
+/// Handle to the process's standard error, from [`stderr`].
+pub struct Stderr(StdioFd);
+
+pub fn stdin() -> Stdin {
+    Stdin(StdioFd::new(libc::STDIN_FILENO))
+}
+
+pub fn stdout() -> Stdout {
+    Stdout(StdioFd::new(libc::STDOUT_FILENO))
+}
+
+pub fn stderr() -> Stderr {
+    Stderr(StdioFd::new(libc::STDERR_FILENO))
+}
+
+// Regular files go through the ring. TTYs, pipes and sockets would park an io-wq worker
+// on a read that may never complete, so those wait for readiness instead and then do the
+// read or write themselves. The fd is left blocking, it is shared with the parent process.
+struct StdioFd {
+    fd: RawFd,
+    poll: Option<AsyncFd<RawFd>>,
+}
+
+impl StdioFd {
+    fn new(fd: RawFd) -> Self {
+        let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+        let seekable = unsafe { libc::fstat(fd, &mut stat) } == 0
+            && matches!(stat.st_mode & libc::S_IFMT, libc::S_IFREG | libc::S_IFBLK);
+
+        Self {
+            fd,
+            poll: (!seekable).then(|| AsyncFd::new(fd).unwrap()),
+        }
+    }
+
+    async fn read(&self, buf: &mut [u8]) -> IoResult<usize> {
+        let Some(poll) = &self.poll else {
+            return AsyncReader::new(self.fd, buf).await;
+        };
+
+        loop {
+            // ready right now, the read won't block unless someone else takes the data first
+            if ready_now(self.fd, libc::POLLIN) {
+                let n = unsafe { libc::read(self.fd, buf.as_mut_ptr() as *mut _, buf.len()) };
+                match cvt(n) {
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                    result => return result,
+                }
+            }
+            poll.readable().await?.clear_ready();
+        }
+    }
+
+    async fn read_owned<B: AsMut<[u8]> + 'static>(&self, mut buf: B) -> (IoResult<usize>, B) {
+        match &self.poll {
+            None => read_owned(FdTarget::Raw(self.fd), buf).await,
+            // the read happens synchronously
+            Some(_) => (self.read(buf.as_mut()).await, buf),
+        }
+    }
+
+    async fn write_owned<B: AsRef<[u8]> + 'static>(&self, buf: B) -> (IoResult<usize>, B) {
+        match &self.poll {
+            None => write_owned(FdTarget::Raw(self.fd), buf).await,
+            Some(_) => (self.write(buf.as_ref()).await, buf),
+        }
+    }
+
+    async fn write(&self, buf: &[u8]) -> IoResult<usize> {
+        let Some(poll) = &self.poll else {
+            return AsyncWriter::new(self.fd, buf).await;
+        };
+
+        // a writable pipe has room for at least this much
+        let len = buf.len().min(libc::PIPE_BUF);
+        loop {
+            if ready_now(self.fd, libc::POLLOUT) {
+                let n = unsafe { libc::write(self.fd, buf.as_ptr() as *const _, len) };
+                match cvt(n) {
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                    result => return result,
+                }
+            }
+            poll.writable().await?.clear_ready();
+        }
+    }
+}
+
+// poll(2) with a zero timeout, hang-ups and errors count as ready so the I/O reports them
+fn ready_now(fd: RawFd, events: libc::c_short) -> bool {
+    let mut pollfd = libc::pollfd { fd, events, revents: 0 };
+    unsafe { libc::poll(&mut pollfd, 1, 0) > 0 }
+}
+
+fn cvt(n: isize) -> IoResult<usize> {
+    if n < 0 {
+        Err(IoError::last_os_error())
+    } else {
+        Ok(n as usize)
+    }
+}
+
+impl AsyncRead for Stdin {
+    fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl std::future::Future<Output = IoResult<usize>> + 'a {
+        self.0.read(buf)
+    }
+
+    fn read_owned<B: AsMut<[u8]> + 'static>(&self, buf: B) -> impl std::future::Future<Output = (IoResult<usize>, B)> + '_ {
+        self.0.read_owned(buf)
+    }
+}
+
+impl AsyncWrite for Stdout {
+    fn write<'a>(&'a self, buf: &'a [u8]) -> impl std::future::Future<Output = IoResult<usize>> + 'a {
+        self.0.write(buf)
+    }
+
+    fn write_owned<B: AsRef<[u8]> + 'static>(&self, buf: B) -> impl std::future::Future<Output = (IoResult<usize>, B)> + '_ {
+        self.0.write_owned(buf)
+    }
+}
+
+impl AsyncWrite for Stderr {
+    fn write<'a>(&'a self, buf: &'a [u8]) -> impl std::future::Future<Output = IoResult<usize>> + 'a {
+        self.0.write(buf)
+    }
+
+    fn write_owned<B: AsRef<[u8]> + 'static>(&self, buf: B) -> impl std::future::Future<Output = (IoResult<usize>, B)> + '_ {
+        self.0.write_owned(buf)
+    }
+}
+
+impl AsRawFd for Stdin {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.fd
+    }
+}
+
+impl AsRawFd for Stdout {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.fd
+    }
+}
+
+impl AsRawFd for Stderr {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.fd
+    }
+}
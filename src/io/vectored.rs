@@ -0,0 +1,236 @@
+use std::{
+    future::Future,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    task::{Context, Poll},
+};
+
+use crate::reactor::{FdTarget, ReactorRef};
+
+use super::completion_result;
+
+// what the kernel reads or writes, kept at a stable address until the completion
+struct Iovecs<B> {
+    bufs: Vec<B>,
+    iovecs: Vec<libc::iovec>,
+}
+
+/// Future of a `read_vectored`, resolving to the result and the buffers.
+///
+/// The buffers are filled in order, the count says how far.
+pub struct ReadVectored<B: AsMut<[u8]> + 'static> {
+    fd: FdTarget,
+    data: Option<Box<Iovecs<B>>>,
+    token: Option<u64>,
+    reactor: ReactorRef,
+}
+
+impl<B: AsMut<[u8]> + 'static> ReadVectored<B> {
+    pub(crate) fn new(fd: FdTarget, mut bufs: Vec<B>) -> Self {
+        let iovecs = bufs
+            .iter_mut()
+            .map(|buf| {
+                let buf = buf.as_mut();
+                libc::iovec {
+                    iov_base: buf.as_mut_ptr() as *mut _,
+                    iov_len: buf.len(),
+                }
+            })
+            .collect();
+
+        Self {
+            fd,
+            data: Some(Box::new(Iovecs { bufs, iovecs })),
+            token: None,
+            reactor: ReactorRef::default(),
+        }
+    }
+}
+
+impl<B: AsMut<[u8]> + 'static> Future for ReadVectored<B> {
+    type Output = (IoResult<usize>, Vec<B>);
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let reactor = match self.reactor.get() {
+            Ok(reactor) => reactor,
+            Err(e) => return Poll::Ready((Err(e), self.data.take().unwrap().bufs)),
+        };
+        let mut reactor = reactor.borrow_mut();
+
+        if let Some(token) = self.token {
+            match reactor.take_token_result(token) {
+                Some(result) => {
+                    self.token = None;
+                    Poll::Ready((completion_result(result), self.data.take().unwrap().bufs))
+                }
+                None => Poll::Pending,
+            }
+        } else {
+            let data = self.data.as_ref().unwrap();
+            let (ptr, len) = (data.iovecs.as_ptr(), data.iovecs.len());
+            let token = reactor.readv(self.fd, cx, ptr, len);
+            self.token = Some(token);
+
+            Poll::Pending
+        }
+    }
+}
+
+impl<B: AsMut<[u8]> + 'static> Drop for ReadVectored<B> {
+    fn drop(&mut self) {
+        if let (Some(token), Some(data), Some(reactor)) = (self.token, self.data.take(), self.reactor.bound()) {
+            reactor.borrow_mut().cancel_and_keep(token, data);
+        }
+    }
+}
+
+/// Future of a `write_vectored`, resolving to the result and the buffers.
+pub struct WriteVectored<B: AsRef<[u8]> + 'static> {
+    fd: FdTarget,
+    data: Option<Box<Iovecs<B>>>,
+    token: Option<u64>,
+    reactor: ReactorRef,
+}
+
+impl<B: AsRef<[u8]> + 'static> WriteVectored<B> {
+    pub(crate) fn new(fd: FdTarget, bufs: Vec<B>) -> Self {
+        Self::skipping(fd, bufs, 0)
+    }
+
+    /// Write all but the first `skip` bytes of `bufs`.
+    fn skipping(fd: FdTarget, bufs: Vec<B>, mut skip: usize) -> Self {
+        let mut iovecs = Vec::with_capacity(bufs.len());
+        for buf in &bufs {
+            let buf = buf.as_ref();
+            if skip >= buf.len() {
+                skip -= buf.len();
+                continue;
+            }
+            iovecs.push(libc::iovec {
+                iov_base: buf[skip..].as_ptr() as *mut _,
+                iov_len: buf.len() - skip,
+            });
+            skip = 0;
+        }
+
+        Self {
+            fd,
+            data: Some(Box::new(Iovecs { bufs, iovecs })),
+            token: None,
+            reactor: ReactorRef::default(),
+        }
+    }
+}
+
+impl<B: AsRef<[u8]> + 'static> Future for WriteVectored<B> {
+    type Output = (IoResult<usize>, Vec<B>);
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let reactor = match self.reactor.get() {
+            Ok(reactor) => reactor,
+            Err(e) => return Poll::Ready((Err(e), self.data.take().unwrap().bufs)),
+        };
+        let mut reactor = reactor.borrow_mut();
+
+        if let Some(token) = self.token {
+            match reactor.take_token_result(token) {
+                Some(result) => {
+                    self.token = None;
+                    Poll::Ready((completion_result(result), self.data.take().unwrap().bufs))
+                }
+                None => Poll::Pending,
+            }
+        } else {
+            let data = self.data.as_ref().unwrap();
+            let (ptr, len) = (data.iovecs.as_ptr(), data.iovecs.len());
+            let token = reactor.writev(self.fd, cx, ptr, len);
+            self.token = Some(token);
+
+            Poll::Pending
+        }
+    }
+}
+
+impl<B: AsRef<[u8]> + 'static> Drop for WriteVectored<B> {
+    fn drop(&mut self) {
+        if let (Some(token), Some(data), Some(reactor)) = (self.token, self.data.take(), self.reactor.bound()) {
+            reactor.borrow_mut().cancel_and_keep(token, data);
+        }
+    }
+}
+
+/// A plain read into an owned buffer, which goes to the reactor if dropped in flight.
+pub(crate) async fn read_owned<B: AsMut<[u8]> + 'static>(fd: FdTarget, buf: B) -> (IoResult<usize>, B) {
+    let (result, mut bufs) = ReadVectored::new(fd, vec![buf]).await;
+    (result, bufs.pop().unwrap())
+}
+
+/// A plain write from an owned buffer, which goes to the reactor if dropped in flight.
+pub(crate) async fn write_owned<B: AsRef<[u8]> + 'static>(fd: FdTarget, buf: B) -> (IoResult<usize>, B) {
+    let (result, mut bufs) = WriteVectored::new(fd, vec![buf]).await;
+    (result, bufs.pop().unwrap())
+}
+
+/// Write the whole of `bufs`, resubmitting after short writes. `WriteZero` if nothing more
+/// can be written.
+pub(crate) async fn write_all_vectored<B: AsRef<[u8]> + 'static>(fd: FdTarget, mut bufs: Vec<B>) -> (IoResult<()>, Vec<B>) {
+    let total: usize = bufs.iter().map(|buf| buf.as_ref().len()).sum();
+    let mut written = 0;
+
+    while written < total {
+        let (result, returned) = WriteVectored::skipping(fd, bufs, written).await;
+        bufs = returned;
+        match result {
+            Ok(0) => return (Err(IoError::from(ErrorKind::WriteZero)), bufs),
+            Ok(n) => written += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return (Err(e), bufs),
+        }
+    }
+
+    (Ok(()), bufs)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        executor::Executor,
+        io::{AsyncReadExt, AsyncWrite},
+        net::UnixStream,
+    };
+
+    #[test]
+    fn short_writes_resume_where_they_stopped() {
+        Executor::new().block_on(|| async {
+            let (a, b) = UnixStream::pair().unwrap();
+            // larger than the socket buffer, so the writes come back short
+            let bufs: Vec<Vec<u8>> = [300_001, 1, 400_000, 0, 123_457]
+                .iter()
+                .enumerate()
+                .map(|(i, &len)| (0..len).map(|j| (i * 7 + j % 251) as u8).collect())
+                .collect();
+            let expected = bufs.concat();
+
+            let (n, bufs) = a.write_vectored(bufs).await;
+            assert!(n.unwrap() < expected.len());
+
+            let write = async {
+                let (result, bufs) = a.write_all_vectored(bufs).await;
+                result.unwrap();
+                AsyncWrite::shutdown(&a).await.unwrap();
+                bufs
+            };
+            let read = async {
+                let mut got = Vec::new();
+                b.read_to_end(&mut got).await.unwrap();
+                got
+            };
+            let (bufs, got) = futures::join!(write, read);
+
+            // the first short write, then all of it again
+            let first = got.len() - expected.len();
+            assert_eq!(&got[..first], &expected[..first]);
+            assert_eq!(&got[first..], &expected[..]);
+            assert_eq!(bufs.concat(), expected);
+        });
+    }
+}
@@ -1,6 +1,9 @@
 pub mod executor;
+mod driver;
 mod reactor;
 
+pub mod buf;
+pub mod codec;
 pub mod fs;
 pub mod io;
 pub mod net;
\ No newline at end of file
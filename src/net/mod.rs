@@ -1,3 +1,7 @@
+mod split;
 mod tcp;
+mod unix;
 
-pub use tcp::TcpListener;
\ No newline at end of file
+pub use split::{OwnedReadHalf, OwnedWriteHalf};
+pub use tcp::{Incoming, RecvEvent, RecvStream, SendZc, TcpListener, TcpSteam};
+pub use unix::{UnixListener, UnixStream};
@@ -0,0 +1,140 @@
+use std::{future::Future, io::Result as IoResult, net::Shutdown};
+
+use crate::io::{AsyncRead, AsyncWrite};
+
+use super::TcpSteam;
+
+/// The read half of a [`TcpSteam`], from [`TcpSteam::into_split`].
+pub struct OwnedReadHalf {
+    stream: TcpSteam,
+}
+
+impl OwnedReadHalf {
+    pub(super) fn new(stream: TcpSteam) -> Self {
+        Self { stream }
+    }
+
+    /// Read into `buf`, resolving to 0 once the peer shut down its writes.
+    pub fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        self.stream.read(buf)
+    }
+}
+
+impl AsyncRead for OwnedReadHalf {
+    fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        OwnedReadHalf::read(self, buf)
+    }
+
+    fn read_owned<B: AsMut<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        self.stream.read_owned(buf)
+    }
+}
+
+/// The write half of a [`TcpSteam`], from [`TcpSteam::into_split`]. Shuts down writes
+/// when dropped, so the peer sees the end of the stream even while the read half lives on.
+pub struct OwnedWriteHalf {
+    stream: TcpSteam,
+    shutdown_on_drop: bool,
+}
+
+impl OwnedWriteHalf {
+    pub(super) fn new(stream: TcpSteam) -> Self {
+        Self {
+            stream,
+            shutdown_on_drop: true,
+        }
+    }
+
+    pub fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        self.stream.write(buf)
+    }
+
+    /// Shut down writes now, reporting the error if any.
+    pub async fn shutdown(&mut self) -> IoResult<()> {
+        self.shutdown_on_drop = false;
+        self.stream.shutdown(Shutdown::Write).await
+    }
+
+    /// Drop the half without shutting down writes.
+    pub fn forget(mut self) {
+        self.shutdown_on_drop = false;
+    }
+}
+
+impl AsyncWrite for OwnedWriteHalf {
+    fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        self.stream.write(buf)
+    }
+
+    fn write_owned<B: AsRef<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        self.stream.write_owned(buf)
+    }
+
+    fn shutdown(&self) -> impl Future<Output = IoResult<()>> + '_ {
+        self.stream.shutdown(Shutdown::Write)
+    }
+}
+
+impl Drop for OwnedWriteHalf {
+    fn drop(&mut self) {
+        if self.shutdown_on_drop {
+            self.stream.shutdown_write_detached();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use crate::{
+        executor::Executor,
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[test]
+    fn halves_used_at_once() {
+        let std_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = std_listener.local_addr().unwrap();
+        // echoes until the end of the stream, then shuts down its own writes
+        let echo = std::thread::spawn(move || {
+            let mut client = std::net::TcpStream::connect(addr).unwrap();
+            let mut buf = [0; 4096];
+            loop {
+                let n = client.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                client.write_all(&buf[..n]).unwrap();
+            }
+            client.shutdown(std::net::Shutdown::Write).unwrap();
+        });
+
+        let listener = TcpListener::from(std_listener);
+        Executor::new().block_on(move || {
+            let accept = listener.accept();
+            async move {
+                let (stream, _) = accept.await.unwrap();
+                let (read, write) = stream.into_split();
+                // more than the socket buffers hold, the echo stalls unless both halves progress
+                let data: Vec<u8> = (0..4 << 20).map(|i| (i % 251) as u8).collect();
+
+                let send = async {
+                    write.write_all(&data).await.unwrap();
+                    // the peer sees the end of the stream
+                    drop(write);
+                };
+                let receive = async {
+                    let mut got = Vec::new();
+                    read.read_to_end(&mut got).await.unwrap();
+                    got
+                };
+                let ((), got) = futures::join!(send, receive);
+                assert_eq!(got.len(), data.len());
+                assert!(got == data);
+            }
+        });
+        echo.join().unwrap();
+    }
+}
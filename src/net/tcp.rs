@@ -1,24 +1,26 @@
 use std::{
-    cell::RefCell,
     future::Future,
     io::{Error as IoError, ErrorKind, Result as IoResult},
-    net::{SocketAddr, ToSocketAddrs},
+    net::{Shutdown, SocketAddr, ToSocketAddrs},
     os::fd::{AsRawFd, IntoRawFd, RawFd},
-    rc::{Rc, Weak},
     task::{Context, Poll},
 };
 
-use futures::FutureExt;
+use futures::{FutureExt, Stream};
 use socket2::{Domain, Protocol, Socket, Type};
 
+use super::split::{OwnedReadHalf, OwnedWriteHalf};
 use crate::{
-    io::{AsyncReader, AsyncWriter},
-    reactor::{get_reactor, Reactor},
+    buf::{BufRing, FixedBuf, ProvidedBuf},
+    io::{
+        completion_result, detach, read_owned, write_all_vectored, write_owned, AsyncFixedReader, AsyncFixedWriter, AsyncRead, AsyncReader, AsyncRecvBuf,
+        AsyncWrite, AsyncWriter, LinkFd, ReadVectored, SharedFd, WriteVectored,
+    },
+    reactor::{FdTarget, ReactorRef},
 };
 
 pub struct TcpListener {
-    fd: RawFd,
-    _reactor: Weak<RefCell<Reactor>>,
+    fd: SharedFd,
 }
 
 impl TcpListener {
@@ -26,7 +28,7 @@ impl TcpListener {
         let addr = addr
             .to_socket_addrs()?
             .next()
-            .ok_or_else(|| IoError::new(ErrorKind::Other, "empty address"))?;
+            .ok_or_else(|| IoError::other("empty address"))?;
 
         let domain = if addr.is_ipv6() {
             Domain::IPV6
@@ -39,35 +41,85 @@ impl TcpListener {
         sock.bind(&addr)?;
         sock.listen(1024)?;
 
-        // add fd to reactor
-        let reactor = get_reactor();
-
-        println!("tcp bind with fd {}", sock.as_raw_fd());
         Ok(Self {
-            fd: sock.into_raw_fd(),
-            _reactor: Rc::downgrade(&reactor),
+            fd: SharedFd::new(sock.into_raw_fd()),
         })
     }
 
     pub fn accept(&self) -> TcpAccpeter {
-        TcpAccpeter::new(self.fd)
+        TcpAccpeter::with_target(self.fd.target(), false)
+    }
+
+    /// A stream of accepted connections, driven by a single multishot `IORING_OP_ACCEPT`
+    /// which is re-armed whenever the kernel terminates it.
+    pub fn incoming(&self) -> Incoming<'_> {
+        Incoming::new(self)
+    }
+
+    /// Accept a connection as a direct descriptor, straight into the fixed file table.
+    ///
+    /// The returned stream has no regular fd, so its `as_raw_fd` returns -1.
+    pub fn accept_direct(&self) -> TcpAccpeter {
+        TcpAccpeter::with_target(self.fd.target(), true)
+    }
+
+    /// Register the listener into the ring's fixed file table, later accepts
+    /// are issued against the fixed slot.
+    pub fn register_fixed(&self) -> IoResult<()> {
+        self.fd.register_fixed()
+    }
+
+    pub fn is_fixed(&self) -> bool {
+        self.fd.is_fixed()
+    }
+
+    /// Close the listener, reporting any error returned by the kernel.
+    pub async fn close(self) -> IoResult<()> {
+        self.fd.close().await
+    }
+}
+
+impl From<std::net::TcpListener> for TcpListener {
+    fn from(listener: std::net::TcpListener) -> Self {
+        Self {
+            fd: SharedFd::new(listener.into_raw_fd()),
+        }
+    }
+}
+
+impl AsRawFd for TcpListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
     }
 }
 
 pub struct TcpAccpeter {
-    fd: RawFd,
-    reactor: Weak<RefCell<Reactor>>,
+    fd: FdTarget,
+    reactor: ReactorRef,
     token: Option<u64>,
+    // the kernel writes the peer address here while the accept is in flight
+    socketaddr: Option<Box<(libc::sockaddr_storage, libc::socklen_t)>>,
+    direct: bool,
+    // fixed file table slot reserved for a direct accept
+    slot: Option<u32>,
 }
 
 impl TcpAccpeter {
     pub fn new(fd: RawFd) -> Self {
-        let reactor = get_reactor();
+        Self::with_target(FdTarget::Raw(fd), false)
+    }
 
+    pub(crate) fn with_target(fd: FdTarget, direct: bool) -> Self {
         Self {
             fd,
-            reactor: Rc::downgrade(&reactor),
+            reactor: ReactorRef::default(),
             token: None,
+            socketaddr: Some(Box::new((
+                unsafe { std::mem::zeroed::<libc::sockaddr_storage>() },
+                std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t,
+            ))),
+            direct,
+            slot: None,
         }
     }
 }
@@ -76,27 +128,15 @@ impl Future for TcpAccpeter {
     type Output = IoResult<(TcpSteam, Option<SocketAddr>)>;
 
     fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let reactor = self.reactor.upgrade().unwrap();
-        let mut socketaddr = Box::new((
-            unsafe { std::mem::zeroed::<libc::sockaddr_storage>() },
-            std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t,
-        ));
-
-        if self.token.is_none() {
-            let token = reactor.borrow_mut().accept(
-                self.fd,
-                cx,
-                &mut socketaddr.0 as *mut _ as *mut _,
-                &mut socketaddr.1,
-            );
-
-            self.token = Some(token);
+        let reactor = self.reactor.get()?;
 
-            Poll::Pending
-        } else {
+        if let Some(token) = self.token {
             let mut reactor = reactor.borrow_mut();
-            if let Some(result) = reactor.take_token_result(self.token.unwrap()) {
+            if let Some(result) = reactor.take_token_result(token) {
+                self.token = None;
+                let slot = self.slot.take();
                 if result >= 0 {
+                    let socketaddr = self.socketaddr.take().unwrap();
                     let (_, addr) = unsafe {
                         socket2::SockAddr::init(move |addr_storage, len| {
                             socketaddr.0.clone_into(&mut *addr_storage);
@@ -105,14 +145,20 @@ impl Future for TcpAccpeter {
                         })?
                     };
 
-                    let stream = TcpSteam::new(result as RawFd);
+                    let stream = match slot {
+                        Some(slot) => TcpSteam { fd: SharedFd::new_direct(slot, self.reactor.clone()) },
+                        None => TcpSteam::new(result as RawFd),
+                    };
 
                     Poll::Ready(Ok((stream, addr.as_socket())))
                 } else {
-                    let err_code = -result as i32;
+                    if let Some(slot) = slot {
+                        reactor.release_file_slot(slot);
+                    }
+                    let err_code = -result;
                     let err = match err_code {
                         libc::EAGAIN => IoError::from(ErrorKind::WouldBlock),
-                        _ => IoError::from(ErrorKind::Other),
+                        _ => IoError::from_raw_os_error(err_code),
                     };
 
                     Poll::Ready(Err(err))
@@ -120,25 +166,136 @@ impl Future for TcpAccpeter {
             } else {
                 Poll::Pending
             }
+        } else {
+            let mut reactor = reactor.borrow_mut();
+            if self.direct {
+                match reactor.reserve_file_slot() {
+                    Ok(slot) => self.slot = Some(slot),
+                    Err(e) => return Poll::Ready(Err(e)),
+                }
+            }
+
+            let (fd, slot) = (self.fd, self.slot);
+            let socketaddr = self.socketaddr.as_mut().unwrap();
+            let token = reactor.accept(
+                fd,
+                cx,
+                &mut socketaddr.0 as *mut _ as *mut _,
+                &mut socketaddr.1,
+                slot,
+            );
+
+            self.token = Some(token);
+
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for TcpAccpeter {
+    fn drop(&mut self) {
+        let Some(token) = self.token else {
+            return;
+        };
+        if let Some(reactor) = self.reactor.bound() {
+            let mut reactor = reactor.borrow_mut();
+            match reactor.take_token_result(token) {
+                // accepted, but never handed out
+                Some(result) => match self.slot.take() {
+                    Some(slot) => reactor.release_file_slot(slot),
+                    None if result >= 0 => reactor.close_detached(result),
+                    None => {}
+                },
+                None => {
+                    if let Some(slot) = self.slot.take() {
+                        reactor.release_file_slot_after(token, slot);
+                    }
+                    if let Some(socketaddr) = self.socketaddr.take() {
+                        reactor.cancel_and_keep(token, socketaddr);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub struct Incoming<'a> {
+    listener: &'a TcpListener,
+    reactor: ReactorRef,
+    token: Option<u64>,
+}
+
+impl<'a> Incoming<'a> {
+    pub fn new(listener: &'a TcpListener) -> Self {
+        Self {
+            listener,
+            reactor: ReactorRef::default(),
+            token: None,
+        }
+    }
+}
+
+impl<'a> Stream for Incoming<'a> {
+    type Item = IoResult<TcpSteam>;
+
+    fn poll_next(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let reactor = self.reactor.get()?;
+        let mut reactor = reactor.borrow_mut();
+
+        if let Some(token) = self.token {
+            match reactor.poll_multishot(token, cx.waker()) {
+                Some((result, flags)) => {
+                    if !io_uring::cqueue::more(flags) {
+                        // the kernel stopped the accept, arm a new one on the next poll
+                        self.token = None;
+                    }
+                    if result >= 0 {
+                        Poll::Ready(Some(Ok(TcpSteam::new(result as RawFd))))
+                    } else {
+                        let err = match -result {
+                            libc::EAGAIN => IoError::from(ErrorKind::WouldBlock),
+                            err_code => IoError::from_raw_os_error(err_code),
+                        };
+                        Poll::Ready(Some(Err(err)))
+                    }
+                }
+                None => Poll::Pending,
+            }
+        } else {
+            let token = reactor.accept_multi(self.listener.fd.target(), cx);
+            self.token = Some(token);
+
+            Poll::Pending
         }
     }
 }
 
+impl<'a> Drop for Incoming<'a> {
+    fn drop(&mut self) {
+        let (Some(token), Some(reactor)) = (self.token, self.reactor.bound()) else {
+            return;
+        };
+        // close the connections accepted after the stream went away
+        reactor.borrow_mut().orphan_token(token, |result, _| {
+            if result >= 0 {
+                unsafe { libc::close(result) };
+            }
+        });
+    }
+}
+
 pub struct TcpSteam {
-    fd: RawFd,
-    reactor: Weak<RefCell<Reactor>>,
+    fd: SharedFd,
 }
 
 impl TcpSteam {
     pub fn new(fd: RawFd) -> Self {
-        let reactor = get_reactor();
-
         Self {
-            fd,
-            reactor: Rc::downgrade(&reactor),
+            fd: SharedFd::new(fd),
         }
     }
 
+    /// Read into `buf`, resolving to 0 once the peer shut down its writes.
     pub fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
         TcpStreamReader::new(self, buf)
     }
@@ -146,6 +303,246 @@ impl TcpSteam {
     pub fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
         TcpStreamWriter::new(self, buf)
     }
+
+    /// Read into several buffers with a single `IORING_OP_READV`, filling them in order.
+    pub fn read_vectored<B: AsMut<[u8]> + 'static>(&self, bufs: Vec<B>) -> ReadVectored<B> {
+        ReadVectored::new(self.fd.target(), bufs)
+    }
+
+    /// Write several buffers with a single `IORING_OP_WRITEV`, e.g. a header and a body
+    /// without concatenating them. The count may be short.
+    pub fn write_vectored<B: AsRef<[u8]> + 'static>(&self, bufs: Vec<B>) -> WriteVectored<B> {
+        WriteVectored::new(self.fd.target(), bufs)
+    }
+
+    /// Write the whole of `bufs`, resubmitting after short writes.
+    pub async fn write_all_vectored<B: AsRef<[u8]> + 'static>(&self, bufs: Vec<B>) -> (IoResult<()>, Vec<B>) {
+        write_all_vectored(self.fd.target(), bufs).await
+    }
+
+    /// Read into a registered buffer, filling it up to its capacity. Resolves to the result
+    /// and the buffer.
+    pub fn read_fixed(&self, buf: FixedBuf) -> AsyncFixedReader {
+        AsyncFixedReader::with_target(self.fd.target(), buf, 0)
+    }
+
+    /// Write the content of a registered buffer, resolving to the result and the buffer.
+    pub fn write_fixed(&self, buf: FixedBuf) -> AsyncFixedWriter {
+        AsyncFixedWriter::with_target(self.fd.target(), buf, 0)
+    }
+
+    /// Receive into a buffer the kernel picks from `ring` only once data arrives,
+    /// nothing is pinned while the connection is idle.
+    pub fn recv_buf<'a>(&'a self, ring: &'a BufRing) -> impl Future<Output = IoResult<ProvidedBuf>> + 'a {
+        AsyncRecvBuf::with_target(self.fd.target(), ring)
+    }
+
+    /// Send `buf` without copying it into the kernel, through `IORING_OP_SEND_ZC`.
+    ///
+    /// Like `write` the count may be short. The buffer is given back once the kernel is done
+    /// with it, which may be well after the data was sent. Worth it for large payloads only.
+    pub fn send_zc<B: AsRef<[u8]> + 'static>(&self, buf: B) -> SendZc<B> {
+        SendZc::new(self, buf)
+    }
+
+    /// A stream of received chunks, driven by a single multishot `IORING_OP_RECV` which
+    /// takes its buffers from `ring`. It ends once the peer closes the connection.
+    pub fn recv_stream<'a>(&'a self, ring: &'a BufRing) -> RecvStream<'a> {
+        RecvStream::new(self, ring)
+    }
+
+    /// Register the stream into the ring's fixed file table, later operations
+    /// are issued against the fixed slot instead of the fd.
+    pub fn register_fixed(&self) -> IoResult<()> {
+        self.fd.register_fixed()
+    }
+
+    pub fn is_fixed(&self) -> bool {
+        self.fd.is_fixed()
+    }
+
+    /// The fd of a socket accepted by `TcpAccpeter` on a listener of another family.
+    pub(super) fn into_fd(self) -> SharedFd {
+        self.fd
+    }
+
+    /// Split into halves which can be moved to different tasks, sharing the fd.
+    ///
+    /// The connection is closed once both are dropped, dropping the write half shuts down
+    /// writes right away.
+    pub fn into_split(self) -> (OwnedReadHalf, OwnedWriteHalf) {
+        let read = TcpSteam { fd: self.fd.clone() };
+        (OwnedReadHalf::new(read), OwnedWriteHalf::new(self))
+    }
+
+    /// Shut down writes without waiting for it, for `Drop`.
+    pub(super) fn shutdown_write_detached(&self) {
+        match self.fd.target() {
+            FdTarget::Raw(fd) => unsafe {
+                libc::shutdown(fd, libc::SHUT_WR);
+            },
+            FdTarget::Fixed(_) => {
+                let stream = TcpSteam { fd: self.fd.clone() };
+                detach(async move {
+                    let _ = stream.shutdown(Shutdown::Write).await;
+                });
+            }
+        }
+    }
+
+    /// Shut down the read half, the write half or both halves of the connection.
+    pub async fn shutdown(&self, how: Shutdown) -> IoResult<()> {
+        let how = match how {
+            Shutdown::Read => libc::SHUT_RD,
+            Shutdown::Write => libc::SHUT_WR,
+            Shutdown::Both => libc::SHUT_RDWR,
+        };
+        match self.fd.target() {
+            // doesn't block, no need for a round trip through the ring
+            FdTarget::Raw(fd) => {
+                if unsafe { libc::shutdown(fd, how) } < 0 {
+                    Err(IoError::last_os_error())
+                } else {
+                    Ok(())
+                }
+            }
+            // a direct descriptor can only be used from the ring
+            FdTarget::Fixed(slot) => {
+                let sqe = io_uring::opcode::Shutdown::new(io_uring::types::Fixed(slot), how).build();
+                // the SQE doesn't point to any memory
+                let completion = unsafe { crate::io::raw::submit(sqe) }.await?;
+                completion.io_result().map(drop)
+            }
+        }
+    }
+
+    /// Close the stream, reporting any error returned by the kernel.
+    ///
+    /// Dropping a `TcpSteam` closes it as well, but the result is discarded.
+    pub async fn close(self) -> IoResult<()> {
+        self.fd.close().await
+    }
+}
+
+impl AsyncRead for TcpSteam {
+    fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        TcpSteam::read(self, buf)
+    }
+
+    fn read_owned<B: AsMut<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        read_owned(self.fd.target(), buf)
+    }
+}
+
+impl AsyncWrite for TcpSteam {
+    fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        TcpSteam::write(self, buf)
+    }
+
+    fn write_owned<B: AsRef<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        write_owned(self.fd.target(), buf)
+    }
+
+    fn shutdown(&self) -> impl Future<Output = IoResult<()>> + '_ {
+        TcpSteam::shutdown(self, Shutdown::Write)
+    }
+}
+
+impl From<&TcpSteam> for LinkFd {
+    fn from(stream: &TcpSteam) -> Self {
+        LinkFd::from_target(stream.fd.target())
+    }
+}
+
+impl AsRawFd for TcpSteam {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// An item of a [`RecvStream`].
+pub enum RecvEvent {
+    /// A received chunk, the buffer goes back to the ring when dropped.
+    Data(ProvidedBuf),
+    /// The ring ran out of buffers and the kernel stopped receiving. The receive is re-armed
+    /// on the next poll, which only makes progress once some buffers have been dropped.
+    NoBuffers,
+}
+
+pub struct RecvStream<'a> {
+    stream: &'a TcpSteam,
+    ring: &'a BufRing,
+    reactor: ReactorRef,
+    token: Option<u64>,
+    done: bool,
+}
+
+impl<'a> RecvStream<'a> {
+    pub fn new(stream: &'a TcpSteam, ring: &'a BufRing) -> Self {
+        Self {
+            stream,
+            ring,
+            reactor: ReactorRef::default(),
+            token: None,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Stream for RecvStream<'a> {
+    type Item = IoResult<RecvEvent>;
+
+    fn poll_next(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        let reactor = self.reactor.get()?;
+        let mut reactor = reactor.borrow_mut();
+
+        if let Some(token) = self.token {
+            match reactor.poll_multishot(token, cx.waker()) {
+                Some((result, flags)) => {
+                    if !io_uring::cqueue::more(flags) {
+                        // the kernel stopped the receive, arm a new one on the next poll
+                        self.token = None;
+                    }
+                    let buf = io_uring::cqueue::buffer_select(flags).map(|bid| self.ring.take(bid, result.max(0) as usize));
+                    match (result, buf) {
+                        (0, _) => {
+                            self.done = true;
+                            Poll::Ready(None)
+                        }
+                        (_, Some(buf)) if result > 0 => Poll::Ready(Some(Ok(RecvEvent::Data(buf)))),
+                        (result, _) if result == -libc::ENOBUFS => Poll::Ready(Some(Ok(RecvEvent::NoBuffers))),
+                        (result, _) if result < 0 => Poll::Ready(Some(Err(IoError::from_raw_os_error(-result)))),
+                        _ => Poll::Ready(Some(Err(IoError::other("no buffer selected for the receive")))),
+                    }
+                }
+                None => Poll::Pending,
+            }
+        } else {
+            let token = reactor.recv_multi(self.stream.fd.target(), cx, self.ring.bgid());
+            self.token = Some(token);
+
+            Poll::Pending
+        }
+    }
+}
+
+impl<'a> Drop for RecvStream<'a> {
+    fn drop(&mut self) {
+        let (Some(token), Some(reactor)) = (self.token, self.reactor.bound()) else {
+            return;
+        };
+        // give back the buffers filled after the stream went away
+        let ring = self.ring.clone();
+        reactor.borrow_mut().orphan_token(token, move |result, flags| {
+            if let Some(bid) = io_uring::cqueue::buffer_select(flags) {
+                drop(ring.take(bid, result.max(0) as usize));
+            }
+        });
+    }
 }
 
 pub struct TcpStreamReader<'a> {
@@ -154,7 +551,7 @@ pub struct TcpStreamReader<'a> {
 
 impl<'a> TcpStreamReader<'a> {
     pub fn new(stream: &'a TcpSteam, buf: &'a mut [u8]) -> Self {
-        Self { reader: AsyncReader::new(stream.fd, buf) }
+        Self { reader: AsyncReader::with_target(stream.fd.target(), buf) }
     }
 }
 
@@ -162,28 +559,7 @@ impl<'a> Future for TcpStreamReader<'a> {
     type Output = IoResult<usize>;
 
     fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-       match self.reader.poll_unpin(cx) {
-           Poll::Ready(Ok(n)) => {
-                if n > 0 {
-                    Poll::Ready(Ok(n))
-                } else {
-                    Poll::Ready(Err(IoError::from(ErrorKind::UnexpectedEof)))
-                }
-           }
-           Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
-           Poll::Pending => Poll::Pending,
-       }
-    }
-}
-
-impl Drop for TcpSteam {
-    fn drop(&mut self) {
-        unsafe {
-            let reactor = self.reactor.upgrade().unwrap();
-            reactor.borrow_mut().unregister_fd(self.fd);
-
-            libc::close(self.fd);
-        }
+       self.reader.poll_unpin(cx)
     }
 }
 
@@ -194,7 +570,7 @@ pub struct TcpStreamWriter<'a> {
 
 impl<'a> TcpStreamWriter<'a> {
     pub fn new(stream: &'a TcpSteam, buf: &'a [u8]) -> Self {
-        Self { writer: AsyncWriter::new(stream.fd, buf) }
+        Self { writer: AsyncWriter::with_target(stream.fd.target(), buf) }
     }
 }
 
@@ -208,4 +584,75 @@ impl<'a> Future for TcpStreamWriter<'a> {
            Poll::Pending => Poll::Pending,
        }
     }
-}
\ No newline at end of file
+}
+
+/// Future returned by [`TcpSteam::send_zc`], resolving to the send result and the buffer.
+pub struct SendZc<B: AsRef<[u8]> + 'static> {
+    fd: FdTarget,
+    // boxed so the kernel keeps a stable address even if `B` stores the bytes inline
+    buf: Option<Box<B>>,
+    // result of the send, waiting for the notification
+    sent: Option<i32>,
+    token: Option<u64>,
+    reactor: ReactorRef,
+}
+
+impl<B: AsRef<[u8]> + 'static> SendZc<B> {
+    fn new(stream: &TcpSteam, buf: B) -> Self {
+        Self {
+            fd: stream.fd.target(),
+            buf: Some(Box::new(buf)),
+            sent: None,
+            token: None,
+            reactor: ReactorRef::default(),
+        }
+    }
+}
+
+impl<B: AsRef<[u8]> + 'static> Future for SendZc<B> {
+    type Output = (IoResult<usize>, B);
+
+    fn poll(mut self: std::pin::Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let reactor = match self.reactor.get() {
+            Ok(reactor) => reactor,
+            Err(e) => return Poll::Ready((Err(e), *self.buf.take().unwrap())),
+        };
+        let mut reactor = reactor.borrow_mut();
+
+        if let Some(token) = self.token {
+            while let Some((result, flags)) = reactor.poll_multishot(token, cx.waker()) {
+                let sent = *self.sent.get_or_insert(result);
+                // the notification, or a failed send that won't get one
+                if !io_uring::cqueue::more(flags) {
+                    self.token = None;
+                    let buf = *self.buf.take().unwrap();
+                    return Poll::Ready((completion_result(sent), buf));
+                }
+            }
+            Poll::Pending
+        } else {
+            if let Err(e) = reactor.capabilities().require(reactor.capabilities().send_zc(), "zero-copy send") {
+                return Poll::Ready((Err(e), *self.buf.take().unwrap()));
+            }
+
+            let buf = B::as_ref(self.buf.as_ref().unwrap());
+            let (ptr, len) = (buf.as_ptr(), buf.len());
+            let token = reactor.send_zc(self.fd, cx, ptr, len);
+            self.token = Some(token);
+
+            Poll::Pending
+        }
+    }
+}
+
+impl<B: AsRef<[u8]> + 'static> Drop for SendZc<B> {
+    fn drop(&mut self) {
+        let (Some(token), Some(reactor)) = (self.token, self.reactor.bound()) else {
+            return;
+        };
+        // the kernel may read the buffer until the notification
+        if let Some(buf) = self.buf.take() {
+            reactor.borrow_mut().cancel_and_keep(token, buf);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    future::Future,
+    io::{Error as IoError, Result as IoResult},
+    net::Shutdown,
+    os::{
+        fd::{AsRawFd, IntoRawFd, RawFd},
+        unix::net,
+    },
+    path::Path,
+};
+
+use super::tcp::TcpAccpeter;
+use crate::io::{
+    read_owned, write_all_vectored, write_owned, AsyncRead, AsyncReader, AsyncWrite, AsyncWriter, LinkFd, ReadVectored, SharedFd, WriteVectored,
+};
+
+pub struct UnixListener {
+    fd: SharedFd,
+}
+
+impl UnixListener {
+    /// Bind a listener to the socket file at `path`, which must not exist yet.
+    pub fn bind<P: AsRef<Path>>(path: P) -> IoResult<Self> {
+        let listener = net::UnixListener::bind(path)?;
+
+        Ok(Self {
+            fd: SharedFd::new(listener.into_raw_fd()),
+        })
+    }
+
+    pub async fn accept(&self) -> IoResult<UnixStream> {
+        let (stream, _) = TcpAccpeter::with_target(self.fd.target(), false).await?;
+
+        Ok(UnixStream { fd: stream.into_fd() })
+    }
+
+    /// Close the listener, reporting any error returned by the kernel.
+    pub async fn close(self) -> IoResult<()> {
+        self.fd.close().await
+    }
+}
+
+impl AsRawFd for UnixListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+pub struct UnixStream {
+    fd: SharedFd,
+}
+
+impl UnixStream {
+    /// Connect to the socket file at `path`.
+    ///
+    /// A local connect doesn't wait on a peer, it either succeeds or fails right away,
+    /// so it's done with a plain connect(2).
+    pub fn connect<P: AsRef<Path>>(path: P) -> IoResult<Self> {
+        Ok(net::UnixStream::connect(path)?.into())
+    }
+
+    /// A pair of connected sockets, e.g. to talk to a child process.
+    pub fn pair() -> IoResult<(Self, Self)> {
+        let (a, b) = net::UnixStream::pair()?;
+        Ok((a.into(), b.into()))
+    }
+
+    /// Read into `buf`, resolving to 0 once the peer shut down its writes.
+    pub fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        AsyncReader::with_target(self.fd.target(), buf)
+    }
+
+    pub fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        AsyncWriter::with_target(self.fd.target(), buf)
+    }
+
+    /// Read into several buffers with a single `IORING_OP_READV`, filling them in order.
+    pub fn read_vectored<B: AsMut<[u8]> + 'static>(&self, bufs: Vec<B>) -> ReadVectored<B> {
+        ReadVectored::new(self.fd.target(), bufs)
+    }
+
+    /// Write several buffers with a single `IORING_OP_WRITEV`. The count may be short.
+    pub fn write_vectored<B: AsRef<[u8]> + 'static>(&self, bufs: Vec<B>) -> WriteVectored<B> {
+        WriteVectored::new(self.fd.target(), bufs)
+    }
+
+    /// Write the whole of `bufs`, resubmitting after short writes.
+    pub async fn write_all_vectored<B: AsRef<[u8]> + 'static>(&self, bufs: Vec<B>) -> (IoResult<()>, Vec<B>) {
+        write_all_vectored(self.fd.target(), bufs).await
+    }
+
+    /// Shut down the read half, the write half or both halves of the connection.
+    pub fn shutdown(&self, how: Shutdown) -> IoResult<()> {
+        let how = match how {
+            Shutdown::Read => libc::SHUT_RD,
+            Shutdown::Write => libc::SHUT_WR,
+            Shutdown::Both => libc::SHUT_RDWR,
+        };
+        // never a direct descriptor, and doesn't block
+        if unsafe { libc::shutdown(self.fd.as_raw_fd(), how) } < 0 {
+            return Err(IoError::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    /// Close the stream, reporting any error returned by the kernel.
+    pub async fn close(self) -> IoResult<()> {
+        self.fd.close().await
+    }
+}
+
+impl From<net::UnixStream> for UnixStream {
+    fn from(stream: net::UnixStream) -> Self {
+        Self {
+            fd: SharedFd::new(stream.into_raw_fd()),
+        }
+    }
+}
+
+impl AsyncRead for UnixStream {
+    fn read<'a>(&'a self, buf: &'a mut [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        UnixStream::read(self, buf)
+    }
+
+    fn read_owned<B: AsMut<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        read_owned(self.fd.target(), buf)
+    }
+}
+
+impl AsyncWrite for UnixStream {
+    fn write<'a>(&'a self, buf: &'a [u8]) -> impl Future<Output = IoResult<usize>> + 'a {
+        UnixStream::write(self, buf)
+    }
+
+    fn write_owned<B: AsRef<[u8]> + 'static>(&self, buf: B) -> impl Future<Output = (IoResult<usize>, B)> + '_ {
+        write_owned(self.fd.target(), buf)
+    }
+
+    fn shutdown(&self) -> impl Future<Output = IoResult<()>> + '_ {
+        std::future::ready(UnixStream::shutdown(self, Shutdown::Write))
+    }
+}
+
+impl From<&UnixStream> for LinkFd {
+    fn from(stream: &UnixStream) -> Self {
+        LinkFd::from_target(stream.fd.target())
+    }
+}
+
+impl AsRawFd for UnixStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        executor::Executor,
+        io::{AsyncReadExt, AsyncWriteExt},
+    };
+
+    #[test]
+    fn pair_both_directions() {
+        Executor::new().block_on(|| async {
+            let (a, b) = UnixStream::pair().unwrap();
+            a.write_all(b"ping").await.unwrap();
+            AsyncWrite::shutdown(&a).await.unwrap();
+
+            let mut got = Vec::new();
+            b.read_to_end(&mut got).await.unwrap();
+            assert_eq!(got, b"ping");
+        });
+    }
+
+    #[test]
+    fn accept_a_connection() {
+        let path = std::env::temp_dir().join(format!("aruntime-unix-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        Executor::new().block_on(move || {
+            let path = path.clone();
+            async move {
+                let listener = UnixListener::bind(&path).unwrap();
+                let client = UnixStream::connect(&path).unwrap();
+                let server = listener.accept().await.unwrap();
+                std::fs::remove_file(&path).unwrap();
+
+                client.write_all(b"hello").await.unwrap();
+                let mut buf = [0; 5];
+                server.read_exact(&mut buf).await.unwrap();
+                assert_eq!(&buf, b"hello");
+            }
+        });
+    }
+}
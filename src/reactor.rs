@@ -1,44 +1,162 @@
 use std::{
-    cell::RefCell,
-    os::unix::prelude::{AsRawFd, RawFd},
-    rc::Rc,
+    any::Any,
+    cell::{OnceCell, RefCell},
+    collections::VecDeque,
+    io,
+    os::fd::{FromRawFd, OwnedFd, RawFd},
+    rc::{Rc, Weak},
     task::{Context, Waker},
 };
 
-use io_uring::{opcode, types, IoUring};
+use io_uring::{cqueue, opcode, squeue, types, IoUring};
 
+use crate::driver::{self, Capabilities, Driver, DriverKind, Op};
+
+/// `user_data` of internal SQEs (e.g. cancellations, closes on drop) whose CQEs are discarded.
+pub(crate) const IGNORED_TOKEN: u64 = u64::MAX;
+
+/// Marks `waker_mapping` keys of fixed file slots, so they never collide with regular fds.
+const FIXED_KEY: u64 = 1 << 32;
+
+/// Default size of the fixed file table, registered sparse once as the kernel can't resize it
+/// while it holds direct descriptors. Capped to `RLIMIT_NOFILE`, which the kernel enforces.
+const FIXED_FILES_MAX: u32 = 64 * 1024;
+
+/// The file an operation is issued against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum FdTarget {
+    /// A regular file descriptor, issued with `types::Fd`.
+    Raw(RawFd),
+    /// A slot of the fixed file table, issued with `types::Fixed`.
+    Fixed(u32),
+}
+
+impl FdTarget {
+    fn key(self) -> u64 {
+        match self {
+            FdTarget::Raw(fd) => fd as u32 as u64,
+            FdTarget::Fixed(slot) => FIXED_KEY | slot as u64,
+        }
+    }
+}
+
+impl From<RawFd> for FdTarget {
+    fn from(fd: RawFd) -> Self {
+        FdTarget::Raw(fd)
+    }
+}
+
+/// Build an SQE against a [`FdTarget`], `$fd` is bound to `types::Fd` or `types::Fixed`.
+macro_rules! with_target {
+    ($target:expr, |$fd:ident| $build:expr) => {
+        match $target {
+            $crate::reactor::FdTarget::Raw(raw) => {
+                let $fd = ::io_uring::types::Fd(raw);
+                $build
+            }
+            $crate::reactor::FdTarget::Fixed(slot) => {
+                let $fd = ::io_uring::types::Fixed(slot);
+                $build
+            }
+        }
+    };
+}
+pub(crate) use with_target;
+
+/// The reactor of the running executor, an error outside of `block_on`.
 #[inline]
-pub(crate) fn get_reactor() -> Rc<RefCell<Reactor>> {
-    crate::executor::EX.with(|ex| ex.reactor.clone())
+pub(crate) fn current_reactor() -> io::Result<Rc<RefCell<Reactor>>> {
+    if !crate::executor::EX.is_set() {
+        return Err(io::Error::other("no aruntime executor is running, this must be used inside `Executor::block_on`"));
+    }
+    Ok(crate::executor::EX.with(|ex| ex.reactor.clone()))
 }
 
-pub struct Reactor {
-    waker_mapping: rustc_hash::FxHashMap<u64, Vec<usize>>, // fd -> vec![waker_id], waker_id == token
-    wakers: Vec<Option<(i32, Waker)>>, // waker_id -> (fd, waker)
-    tokens_completion_result: Vec<Option<i32>>, // token -> result of completion
+/// The reactor a resource or future is bound to, the one of the executor it is first used on.
+///
+/// Lets them be created outside of `block_on`, e.g. while setting up a server in `main`.
+#[derive(Clone, Default)]
+pub(crate) struct ReactorRef(OnceCell<Weak<RefCell<Reactor>>>);
+
+impl ReactorRef {
+    pub(crate) fn bound_to(reactor: &Rc<RefCell<Reactor>>) -> Self {
+        Self(OnceCell::from(Rc::downgrade(reactor)))
+    }
+
+    /// The bound reactor, binding to the running executor on first use.
+    pub(crate) fn get(&self) -> io::Result<Rc<RefCell<Reactor>>> {
+        let reactor = match self.0.get() {
+            Some(reactor) => reactor,
+            None => {
+                let current = current_reactor()?;
+                self.0.get_or_init(|| Rc::downgrade(&current))
+            }
+        };
+        reactor.upgrade().ok_or_else(|| io::Error::other("the executor this was first used on is gone"))
+    }
 
-    uring: IoUring,
+    /// The bound reactor if any, for cleanups which have nothing to do if it was never used.
+    pub(crate) fn bound(&self) -> Option<Rc<RefCell<Reactor>>> {
+        self.0.get()?.upgrade()
+    }
+}
 
+pub struct Reactor {
+    waker_mapping: rustc_hash::FxHashMap<u64, Vec<usize>>, // fd key -> vec![waker_id], waker_id == token
+    wakers: Vec<Option<(u64, Waker)>>, // waker_id -> (fd key, waker)
+    tokens_completion_result: Vec<Option<(i32, u32)>>, // token -> (result, flags) of completion
+    multishot_results: rustc_hash::FxHashMap<u64, VecDeque<(i32, u32)>>, // multishot token -> pending (result, flags)
+    orphans: rustc_hash::FxHashMap<u64, Box<dyn FnMut(i32, u32)>>, // token -> cleanup for each completion of a dropped op
+
+    driver: Box<dyn Driver>,
+    capabilities: Capabilities,
+    completions: Vec<(u64, i32, u32)>,
+
+    files: FileTable,
+    free_buf_groups: Vec<u16>,
+    next_buf_group: u16,
+    spare_pipes: Vec<(OwnedFd, OwnedFd)>, // empty (read, write) pipes for splicing
 }
 
 impl Reactor {
+    /// Use io_uring, or fall back to epoll when the kernel doesn't offer it.
     pub fn new() -> Self {
+        let driver = driver::new(DriverKind::IoUring)
+            .or_else(|_| driver::new(DriverKind::Epoll))
+            .expect("neither io_uring nor epoll is available");
+        Self::with_driver(driver)
+    }
+
+    pub fn with_driver_kind(kind: DriverKind) -> io::Result<Self> {
+        Ok(Self::with_driver(driver::new(kind)?))
+    }
+
+    fn with_driver(driver: Box<dyn Driver>) -> Self {
         Self {
             waker_mapping: Default::default(),
             wakers: Vec::new(),
             tokens_completion_result: Vec::new(),
+            multishot_results: Default::default(),
+            orphans: Default::default(),
+
+            capabilities: driver.capabilities(),
+            driver,
+            completions: Vec::new(),
 
-            uring: IoUring::new(128).unwrap(),
+            files: FileTable::default(),
+            free_buf_groups: Vec::new(),
+            next_buf_group: 0,
+            spare_pipes: Vec::new(),
         }
     }
     
-    fn register_waker(&mut self, fd: RawFd, waker: Waker) -> u64 {
+    fn register_waker(&mut self, key: u64, waker: Waker) -> u64 {
         // waker id
         let token = self.wakers.len();
-        self.wakers.push(Some((fd, waker)));
+        self.wakers.push(Some((key, waker)));
 
         // register waker
-        let waker_list = self.waker_mapping.entry(fd as u64).or_insert_with(Vec::new);
+        let waker_list = self.waker_mapping.entry(key).or_default();
         waker_list.push(token);
 
         self.tokens_completion_result.push(None);
@@ -46,90 +164,499 @@ impl Reactor {
         token as u64
     }
 
-    pub(crate) fn unregister_fd(&mut self, fd: RawFd) {
-        if let Some(tokens) = self.waker_mapping.get(&(fd as u64)) {
+    /// Like `register_waker`, for an operation posting completions until one comes without `IORING_CQE_F_MORE`.
+    fn register_multishot_waker(&mut self, key: u64, waker: Waker) -> u64 {
+        let token = self.register_waker(key, waker);
+        self.multishot_results.insert(token, VecDeque::new());
+
+        token
+    }
+
+    /// Cancel every operation still in flight on `fd`.
+    ///
+    /// The wakers are kept, so pending futures are woken by the `ECANCELED`
+    /// completion instead of waiting forever.
+    pub(crate) fn unregister_fd(&mut self, fd: impl Into<FdTarget>) {
+        if let Some(tokens) = self.waker_mapping.remove(&fd.into().key()) {
             for token in tokens {
-                self.wakers[*token] = None;
+                self.driver.cancel(token as u64);
             }
+            // make sure queued operations grab the fd before the caller closes it
+            self.driver.submit();
+        }
+    }
 
-            self.waker_mapping.remove(&(fd as u64));
+    /// Submit `sqes` as one chain linked with `IOSQE_IO_LINK`, or `IOSQE_IO_HARDLINK` when `hard`
+    /// so a failing step doesn't cancel the rest. Returns one token per step.
+    pub(crate) fn link(&mut self, cx: &mut Context, sqes: Vec<(FdTarget, squeue::Entry)>, hard: bool) -> io::Result<Vec<u64>> {
+        let mut tokens = Vec::with_capacity(sqes.len());
+        let mut ops = Vec::with_capacity(sqes.len());
+        for (fd, sqe) in sqes {
+            let token = self.register_waker(fd.key(), cx.waker().clone());
+            ops.push((token, Op::Uring(sqe)));
+            tokens.push(token);
         }
+        if let Err(e) = self.driver.push_link(ops, hard) {
+            for &token in &tokens {
+                self.forget_token(token);
+            }
+            return Err(e);
+        }
+
+        Ok(tokens)
     }
-    
+
     #[allow(dead_code)]
-    pub(crate) fn fsync(&mut self, fd: impl AsRawFd, cx: &mut Context) -> u64 {
-        let token = self.register_waker(fd.as_raw_fd(), cx.waker().clone());
+    pub(crate) fn fsync(&mut self, fd: impl Into<FdTarget>, cx: &mut Context) -> u64 {
+        let fd = fd.into();
+        let token = self.register_waker(fd.key(), cx.waker().clone());
 
-        let sqe = opcode::Fsync::new(types::Fd(fd.as_raw_fd())).build().user_data(token);
-        unsafe { self.uring.submission().push(&sqe).unwrap() };
+        self.driver.push(token, Op::Fsync { fd });
 
         token
     }
 
-    pub(crate) fn read(&mut self, fd: impl AsRawFd, cx: &mut Context, buf: *mut u8, len: usize) -> u64 {
-        let token = self.register_waker(fd.as_raw_fd(), cx.waker().clone());
+    pub(crate) fn read(&mut self, fd: impl Into<FdTarget>, cx: &mut Context, buf: *mut u8, len: usize) -> u64 {
+        let fd = fd.into();
+        let token = self.register_waker(fd.key(), cx.waker().clone());
 
-        let sqe = opcode::Read::new(types::Fd(fd.as_raw_fd()), buf, len as u32).build().user_data(token);
-        unsafe { self.uring.submission().push(&sqe).unwrap() };
+        // -1: at the current file position, like read(2)
+        self.driver.push(token, Op::Read { fd, buf, len: len as u32, offset: u64::MAX });
 
         token
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn readv(&mut self, fd: impl AsRawFd, cx: &mut Context, bufs: *const libc::iovec, bufs_len: usize)-> u64 {
-        let token = self.register_waker(fd.as_raw_fd(), cx.waker().clone());
+    pub(crate) fn readv(&mut self, fd: impl Into<FdTarget>, cx: &mut Context, bufs: *const libc::iovec, bufs_len: usize)-> u64 {
+        let fd = fd.into();
+        let token = self.register_waker(fd.key(), cx.waker().clone());
 
-        let sqe = opcode::Readv::new(types::Fd(fd.as_raw_fd()), bufs, bufs_len as u32).build().user_data(token);
-        unsafe { self.uring.submission().push(&sqe).unwrap() };
+        self.driver.push(token, Op::Readv { fd, iovecs: bufs, len: bufs_len as u32 });
 
         token
     }
 
-    pub(crate) fn write(&mut self, fd: impl AsRawFd, cx: &mut Context, buf: *const u8, len: usize) -> u64 {
-        let token = self.register_waker(fd.as_raw_fd(), cx.waker().clone());
+    pub(crate) fn write(&mut self, fd: impl Into<FdTarget>, cx: &mut Context, buf: *const u8, len: usize) -> u64 {
+        let fd = fd.into();
+        let token = self.register_waker(fd.key(), cx.waker().clone());
 
-        let sqe = opcode::Write::new(types::Fd(fd.as_raw_fd()), buf, len as u32).build().user_data(token);
-        unsafe { self.uring.submission().push(&sqe).unwrap() };
+        self.driver.push(token, Op::Write { fd, buf, len: len as u32, offset: u64::MAX });
 
         token
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn writev(&mut self, fd: impl AsRawFd, cx: &mut Context, bufs: *const libc::iovec, bufs_len: usize) -> u64 {
-        let token = self.register_waker(fd.as_raw_fd(), cx.waker().clone());
+    pub(crate) fn read_fixed(&mut self, fd: impl Into<FdTarget>, cx: &mut Context, buf: *mut u8, len: usize, buf_index: u16, offset: u64) -> u64 {
+        let fd = fd.into();
+        let token = self.register_waker(fd.key(), cx.waker().clone());
+
+        let sqe = with_target!(fd, |fd| opcode::ReadFixed::new(fd, buf, len as u32, buf_index).offset(offset).build());
+        self.driver.push(token, Op::Uring(sqe));
+
+        token
+    }
+
+    pub(crate) fn write_fixed(&mut self, fd: impl Into<FdTarget>, cx: &mut Context, buf: *const u8, len: usize, buf_index: u16, offset: u64) -> u64 {
+        let fd = fd.into();
+        let token = self.register_waker(fd.key(), cx.waker().clone());
+
+        let sqe = with_target!(fd, |fd| opcode::WriteFixed::new(fd, buf, len as u32, buf_index).offset(offset).build());
+        self.driver.push(token, Op::Uring(sqe));
+
+        token
+    }
+
+    pub(crate) fn writev(&mut self, fd: impl Into<FdTarget>, cx: &mut Context, bufs: *const libc::iovec, bufs_len: usize) -> u64 {
+        let fd = fd.into();
+        let token = self.register_waker(fd.key(), cx.waker().clone());
+
+        self.driver.push(token, Op::Writev { fd, iovecs: bufs, len: bufs_len as u32 });
+
+        token
+    }
+
+    pub(crate) fn close(&mut self, fd: impl Into<FdTarget>, cx: &mut Context) -> u64 {
+        let fd = fd.into();
+        let token = self.register_waker(fd.key(), cx.waker().clone());
+
+        self.driver.push(token, Op::Close { fd });
+
+        token
+    }
+
+    /// Close `fd` without waiting for the result, used when a handle is dropped.
+    pub(crate) fn close_detached(&mut self, fd: impl Into<FdTarget>) {
+        let fd = fd.into();
+        self.driver.push(IGNORED_TOKEN, Op::Close { fd });
+    }
+
+    /// Receive into a buffer the kernel picks from the provided buffer group `buf_group`.
+    pub(crate) fn recv_buf_select(&mut self, fd: impl Into<FdTarget>, cx: &mut Context, buf_group: u16, len: usize) -> u64 {
+        let fd = fd.into();
+        let token = self.register_waker(fd.key(), cx.waker().clone());
+
+        let sqe = with_target!(fd, |fd| opcode::Recv::new(fd, std::ptr::null_mut(), len as u32).buf_group(buf_group).build())
+            .flags(squeue::Flags::BUFFER_SELECT);
+        self.driver.push(token, Op::Uring(sqe));
+
+        token
+    }
 
-        let sqe = opcode::Writev::new(types::Fd(fd.as_raw_fd()), bufs, bufs_len as u32).build().user_data(token);
-        unsafe { self.uring.submission().push(&sqe).unwrap() };
+    /// Receive until cancelled, each chunk lands in a buffer picked from `buf_group` and posts
+    /// a completion with `IORING_CQE_F_MORE` set.
+    pub(crate) fn recv_multi(&mut self, fd: impl Into<FdTarget>, cx: &mut Context, buf_group: u16) -> u64 {
+        let fd = fd.into();
+        let token = self.register_multishot_waker(fd.key(), cx.waker().clone());
+
+        let sqe = if self.capabilities.multishot_recv() {
+            with_target!(fd, |fd| opcode::RecvMulti::new(fd, buf_group).build())
+        } else {
+            // a single receive, the caller re-arms it after its only completion
+            with_target!(fd, |fd| opcode::Recv::new(fd, std::ptr::null_mut(), 0).buf_group(buf_group).build())
+                .flags(squeue::Flags::BUFFER_SELECT)
+        };
+        self.driver.push(token, Op::Uring(sqe));
 
         token
     }
 
-    pub(crate) fn accept(&mut self, fd: impl AsRawFd, cx: &mut Context, addr: *mut libc::sockaddr, addr_len: *mut libc::socklen_t) -> u64 {
-        let token = self.register_waker(fd.as_raw_fd(), cx.waker().clone());
+    /// Zero-copy send of `buf`, the send result comes with `IORING_CQE_F_MORE` and is followed
+    /// by a notification once the kernel is done with the buffer.
+    pub(crate) fn send_zc(&mut self, fd: impl Into<FdTarget>, cx: &mut Context, buf: *const u8, len: usize) -> u64 {
+        let fd = fd.into();
+        let token = self.register_multishot_waker(fd.key(), cx.waker().clone());
 
-        let sqe = opcode::Accept::new(types::Fd(fd.as_raw_fd()), addr, addr_len).flags(libc::O_CLOEXEC).build().user_data(token);
-        unsafe { self.uring.submission().push(&sqe).unwrap() };
+        let sqe = with_target!(fd, |fd| opcode::SendZc::new(fd, buf, len as u32).flags(libc::MSG_NOSIGNAL).build());
+        self.driver.push(token, Op::Uring(sqe));
 
         token
     }
 
+    /// Accept a connection, into the fixed file table slot `file_index` if given.
+    pub(crate) fn accept(&mut self, fd: impl Into<FdTarget>, cx: &mut Context, addr: *mut libc::sockaddr, addr_len: *mut libc::socklen_t, file_index: Option<u32>) -> u64 {
+        let fd = fd.into();
+        let token = self.register_waker(fd.key(), cx.waker().clone());
+
+        let op = match file_index {
+            // direct descriptors don't take O_CLOEXEC
+            Some(slot) => Op::Uring(with_target!(fd, |fd| {
+                opcode::Accept::new(fd, addr, addr_len).file_index(Some(destination_slot(slot))).build()
+            })),
+            None => Op::Accept { fd, addr, addr_len },
+        };
+        self.driver.push(token, op);
+
+        token
+    }
+
+    /// Accept connections until cancelled, each one posts a completion with `IORING_CQE_F_MORE` set.
+    pub(crate) fn accept_multi(&mut self, fd: impl Into<FdTarget>, cx: &mut Context) -> u64 {
+        let fd = fd.into();
+        let token = self.register_multishot_waker(fd.key(), cx.waker().clone());
+
+        let op = if self.capabilities.multishot_accept() {
+            Op::Uring(with_target!(fd, |fd| opcode::AcceptMulti::new(fd).flags(libc::O_CLOEXEC).build()))
+        } else {
+            // a single accept, the caller re-arms it after its only completion
+            Op::Accept {
+                fd,
+                addr: std::ptr::null_mut(),
+                addr_len: std::ptr::null_mut(),
+            }
+        };
+        self.driver.push(token, op);
+
+        token
+    }
+
+    /// Poll `fd` for the events in `mask`, with `multi` a completion with `IORING_CQE_F_MORE` is
+    /// posted on every wakeup until cancelled.
+    ///
+    /// Registered as multishot either way, so the result is taken with `poll_multishot`.
+    pub(crate) fn poll_add(&mut self, fd: impl Into<FdTarget>, cx: &mut Context, mask: u32, multi: bool) -> u64 {
+        let fd = fd.into();
+        let token = self.register_multishot_waker(fd.key(), cx.waker().clone());
+
+        let multi = multi && self.capabilities.multishot_poll();
+        self.driver.push(token, Op::Poll { fd, mask, multi });
+
+        token
+    }
+
+    /// Move up to `len` bytes from `fd_in` to `fd_out` at their current positions,
+    /// one of them must be a pipe.
+    pub(crate) fn splice(&mut self, cx: &mut Context, fd_in: RawFd, fd_out: RawFd, len: u32) -> u64 {
+        let token = self.register_waker(FdTarget::Raw(fd_in).key(), cx.waker().clone());
+
+        let sqe = opcode::Splice::new(types::Fd(fd_in), -1, types::Fd(fd_out), -1, len)
+            .flags(libc::SPLICE_F_MOVE)
+            .build();
+        self.driver.push(token, Op::Uring(sqe));
+
+        token
+    }
+
+    /// Duplicate up to `len` bytes from the pipe `fd_in` into the pipe `fd_out`, without consuming them.
+    pub(crate) fn tee(&mut self, cx: &mut Context, fd_in: RawFd, fd_out: RawFd, len: u32) -> u64 {
+        let token = self.register_waker(FdTarget::Raw(fd_in).key(), cx.waker().clone());
+
+        let sqe = opcode::Tee::new(types::Fd(fd_in), types::Fd(fd_out), len).build();
+        self.driver.push(token, Op::Uring(sqe));
+
+        token
+    }
+
+    /// Submit an SQE built outside the crate, only its `user_data` is overwritten.
+    pub(crate) fn submit_raw(&mut self, fd: FdTarget, cx: &mut Context, sqe: squeue::Entry) -> u64 {
+        let token = self.register_waker(fd.key(), cx.waker().clone());
+        self.driver.push(token, Op::Uring(sqe));
+
+        token
+    }
+
+    /// Open `path` relative to the current directory, into the fixed file table slot `file_index` if given.
+    pub(crate) fn openat(&mut self, cx: &mut Context, path: *const libc::c_char, flags: i32, mode: libc::mode_t, file_index: Option<u32>) -> u64 {
+        let dirfd = libc::AT_FDCWD;
+        let token = self.register_waker(FdTarget::Raw(dirfd).key(), cx.waker().clone());
+
+        let open = opcode::OpenAt::new(types::Fd(dirfd), path).mode(mode);
+        let sqe = match file_index {
+            Some(slot) => open.flags(flags & !libc::O_CLOEXEC).file_index(Some(destination_slot(slot))),
+            None => open.flags(flags | libc::O_CLOEXEC),
+        }
+        .build();
+        self.driver.push(token, Op::Uring(sqe));
+
+        token
+    }
+
+    /// Cancel a single operation, its future is woken with `ECANCELED`.
+    pub(crate) fn cancel_token(&mut self, token: u64) {
+        if self.wakers[token as usize].is_some() {
+            self.driver.cancel(token);
+        }
+    }
+
+    /// Cancel `token` and keep `data` alive until its completion arrives, for futures
+    /// dropped while the kernel may still write into memory they own.
+    pub(crate) fn cancel_and_keep(&mut self, token: u64, data: Box<dyn Any>) {
+        // dropped along with the closure after the last completion
+        self.orphan_token(token, move |_, _| {
+            let _ = &data;
+        });
+    }
+
+    /// Cancel `token` whose future was dropped, `on_complete` runs with the result and
+    /// flags of each of its completions, e.g. to give back a buffer the kernel picked.
+    ///
+    /// `on_complete` runs inside `wait`, so it must not touch the reactor.
+    pub(crate) fn orphan_token(&mut self, token: u64, mut on_complete: impl FnMut(i32, u32) + 'static) {
+        // completions that arrived but were never consumed
+        if let Some(results) = self.multishot_results.remove(&token) {
+            for (result, flags) in results {
+                on_complete(result, flags);
+            }
+        } else if let Some((result, flags)) = self.take_token_completion(token) {
+            on_complete(result, flags);
+        }
+
+        if self.wakers[token as usize].is_some() {
+            self.cancel_token(token);
+            self.orphans.insert(token, Box::new(on_complete));
+        }
+    }
+
+    pub(crate) fn driver_kind(&self) -> DriverKind {
+        self.driver.kind()
+    }
+
+    pub(crate) fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    /// The ring, for the features only the io_uring driver has.
+    fn uring(&mut self) -> io::Result<&mut IoUring> {
+        self.driver
+            .uring()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "only supported by the io_uring driver"))
+    }
+
+    /// Forget a token whose operation never made it to the driver.
+    fn forget_token(&mut self, token: u64) {
+        if let Some((key, _)) = self.wakers[token as usize].take() {
+            if let Some(waker_list) = self.waker_mapping.get_mut(&key) {
+                waker_list.retain(|&t| t != token as usize);
+            }
+        }
+        self.multishot_results.remove(&token);
+    }
+
+    /// Size the fixed file table, before its first use.
+    pub(crate) fn set_fixed_files(&mut self, len: u32) {
+        self.files.len = Some(len);
+    }
+
+    /// Register `fd` into the fixed file table and return its slot.
+    pub(crate) fn register_file(&mut self, fd: RawFd) -> io::Result<u32> {
+        let slot = self.alloc_file_slot()?;
+        if let Err(e) = self.uring()?.submitter().register_files_update(slot, &[fd]) {
+            self.files.free.push(slot);
+            return Err(e);
+        }
+
+        Ok(slot)
+    }
+
+    /// Reserve a fixed file table slot for an operation creating a direct descriptor.
+    pub(crate) fn reserve_file_slot(&mut self) -> io::Result<u32> {
+        self.capabilities.require(self.capabilities.direct_descriptors(), "direct descriptors")?;
+        self.alloc_file_slot()
+    }
+
+    /// Clear `slot` and recycle it, this closes a direct descriptor.
+    pub(crate) fn release_file_slot(&mut self, slot: u32) {
+        if let Ok(uring) = self.uring() {
+            let _ = uring.submitter().register_files_update(slot, &[-1]);
+        }
+        self.files.free.push(slot);
+    }
+
+    /// Release `slot` once the operation `token` completes, for direct operations whose
+    /// future was dropped while still in flight.
+    pub(crate) fn release_file_slot_after(&mut self, token: u64, slot: u32) {
+        if self.wakers[token as usize].is_some() {
+            self.cancel_token(token);
+            self.files.release_after.entry(token).or_default().push(slot);
+        } else {
+            self.release_file_slot(slot);
+        }
+    }
+
+    fn alloc_file_slot(&mut self) -> io::Result<u32> {
+        self.capabilities.require(self.capabilities.fixed_files(), "the fixed file table")?;
+        if !self.files.registered {
+            let len = self.files.len.unwrap_or_else(|| FIXED_FILES_MAX.min(nofile_limit()));
+            self.uring()?.submitter().register_files_sparse(len)?;
+            self.files.registered = true;
+            self.files.free.extend((0..len).rev());
+        }
+
+        self.files
+            .free
+            .pop()
+            .ok_or_else(|| io::Error::other("the fixed file table is full, see `executor::Builder::fixed_files`"))
+    }
+
+    /// Register `bufs` as the ring's fixed buffers, only one set can be registered at a time.
+    pub(crate) fn register_buffers(&mut self, bufs: &[libc::iovec]) -> io::Result<()> {
+        unsafe { self.uring()?.submitter().register_buffers(bufs) }
+    }
+
+    pub(crate) fn unregister_buffers(&mut self) -> io::Result<()> {
+        self.uring()?.submitter().unregister_buffers()
+    }
+
+    /// Register a provided buffer ring and return the buffer group id assigned to it.
+    ///
+    /// # Safety
+    ///
+    /// `ring_addr` must point to `entries` page-aligned ring entries, valid until
+    /// the group is unregistered.
+    pub(crate) unsafe fn register_buf_ring(&mut self, ring_addr: u64, entries: u16) -> io::Result<u16> {
+        self.capabilities.require(self.capabilities.provided_buffers(), "provided buffer rings")?;
+        let bgid = match self.free_buf_groups.pop() {
+            Some(bgid) => bgid,
+            None => {
+                let bgid = self.next_buf_group;
+                self.next_buf_group = bgid.checked_add(1).ok_or_else(|| io::Error::other("out of buffer group ids"))?;
+                bgid
+            }
+        };
+
+        if let Err(e) = self.uring()?.submitter().register_buf_ring(ring_addr, entries, bgid) {
+            self.free_buf_groups.push(bgid);
+            return Err(e);
+        }
+
+        Ok(bgid)
+    }
+
+    pub(crate) fn unregister_buf_ring(&mut self, bgid: u16) -> io::Result<()> {
+        self.uring()?.submitter().unregister_buf_ring(bgid)?;
+        self.free_buf_groups.push(bgid);
+
+        Ok(())
+    }
+
+    /// An empty pipe as (read end, write end), reused across splices.
+    pub(crate) fn take_pipe(&mut self) -> io::Result<(OwnedFd, OwnedFd)> {
+        if let Some(pipe) = self.spare_pipes.pop() {
+            return Ok(pipe);
+        }
+
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+    }
+
+    /// Give back a pipe from `take_pipe`, it must be empty.
+    pub(crate) fn recycle_pipe(&mut self, pipe: (OwnedFd, OwnedFd)) {
+        const MAX_SPARE_PIPES: usize = 16;
+        if self.spare_pipes.len() < MAX_SPARE_PIPES {
+            self.spare_pipes.push(pipe);
+        }
+    }
+
     pub fn wait(&mut self) {
-        let _ = self.uring.submit();
-        for cqe in self.uring.completion() {
-            let token = cqe.user_data();
-            let result = cqe.result();
+        let mut completions = std::mem::take(&mut self.completions);
+        self.driver.wait(&mut completions);
+        let mut released_slots = Vec::new();
+        for (token, result, flags) in completions.drain(..) {
 
             // debug
             // println!("CQE token: {:?}", token);
-            let (fd, waker) = self.wakers[token as usize].take().unwrap();
-            // waker had been removed from wakers
-            assert!(self.wakers[token as usize].is_none());
-            // remove this walker from waker_mapping
-            self.waker_mapping.get_mut(&(fd as u64)).unwrap().retain(|&t| t != token as usize);
+            if token == IGNORED_TOKEN {
+                continue;
+            }
+            let more = cqueue::more(flags);
+            if let Some(on_complete) = self.orphans.get_mut(&token) {
+                on_complete(result, flags);
+                if !more {
+                    self.orphans.remove(&token);
+                }
+            }
+            if more {
+                // multishot operation, the token stays registered for the next completions
+                if let Some(results) = self.multishot_results.get_mut(&token) {
+                    results.push_back((result, flags));
+                }
+                if let Some((_, waker)) = &self.wakers[token as usize] {
+                    waker.wake_by_ref();
+                }
+                continue;
+            }
+            // stale completion, nobody is waiting for it anymore
+            let Some((key, waker)) = self.wakers[token as usize].take() else {
+                continue;
+            };
+            // remove this walker from waker_mapping, the fd may have been unregistered already
+            if let Some(waker_list) = self.waker_mapping.get_mut(&key) {
+                waker_list.retain(|&t| t != token as usize);
+            }
+            if let Some(slots) = self.files.release_after.remove(&token) {
+                released_slots.extend(slots);
+            }
             // set result
-            self.tokens_completion_result[token as usize] = Some(result);
+            match self.multishot_results.get_mut(&token) {
+                Some(results) => results.push_back((result, flags)),
+                None => self.tokens_completion_result[token as usize] = Some((result, flags)),
+            }
             waker.wake();
         }
+        self.completions = completions;
+        for slot in released_slots {
+            self.release_file_slot(slot);
+        }
     }
 
     #[allow(dead_code)]
@@ -139,6 +666,32 @@ impl Reactor {
 
     pub(crate) fn take_token_result(&mut self, token: u64) -> Option<i32> {
         // take result
+        self.take_token_completion(token).map(|(result, _)| result)
+    }
+
+    /// Next completion of the multishot operation `token`, `None` if there is none yet.
+    ///
+    /// The operation is over after a completion without `IORING_CQE_F_MORE`.
+    pub(crate) fn poll_multishot(&mut self, token: u64, waker: &Waker) -> Option<(i32, u32)> {
+        let results = self.multishot_results.get_mut(&token)?;
+        if let Some((result, flags)) = results.pop_front() {
+            if !cqueue::more(flags) {
+                self.multishot_results.remove(&token);
+            }
+            return Some((result, flags));
+        }
+
+        // the stream may be polled from another task than the one it was armed from
+        if let Some((_, registered)) = &mut self.wakers[token as usize] {
+            if !registered.will_wake(waker) {
+                *registered = waker.clone();
+            }
+        }
+        None
+    }
+
+    /// Take the result along with the CQE flags, e.g. to find the selected buffer.
+    pub(crate) fn take_token_completion(&mut self, token: u64) -> Option<(i32, u32)> {
         self.tokens_completion_result[token as usize].take()
     }
 }
@@ -147,4 +700,26 @@ impl Default for Reactor {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+// the soft limit, which sparse table registrations may not exceed
+fn nofile_limit() -> u32 {
+    let mut limit: libc::rlimit = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } < 0 {
+        return 1024;
+    }
+    limit.rlim_cur.min(u32::MAX as libc::rlim_t) as u32
+}
+
+fn destination_slot(slot: u32) -> types::DestinationSlot {
+    types::DestinationSlot::try_from_slot_target(slot).expect("fixed file slot out of range")
+}
+
+/// Bookkeeping of the ring's fixed file table.
+#[derive(Default)]
+struct FileTable {
+    len: Option<u32>, // size to register, the default if None
+    registered: bool,
+    free: Vec<u32>,
+    release_after: rustc_hash::FxHashMap<u64, Vec<u32>>, // token -> slots to release once it completes
+}
+